@@ -0,0 +1,291 @@
+use super::Camera;
+use crate::euclidean::{Point3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+// How values are filled in between keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Interpolation {
+    // straight from one key to the next, turning sharply at each key
+    #[default]
+    Linear,
+    // a curve through every key with no sudden turns, for smooth camera moves
+    CatmullRom,
+}
+
+impl Interpolation {
+    // The value at a time from keys at the given times, which must be sorted.
+    // Values before the first key or after the last hold still. None without
+    // any keys, or with a different number of times and values.
+    pub fn at<T>(&self, times: &[f64], values: &[T], time: f64) -> Option<T>
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+    {
+        if values.is_empty() || times.len() != values.len() {
+            return None;
+        }
+        let last = values.len() - 1;
+        if time <= times[0] {
+            return Some(values[0]);
+        }
+        if time >= times[last] {
+            return Some(values[last]);
+        }
+        // the key the segment holding the time starts at
+        let i = times.partition_point(|t| *t <= time) - 1;
+        let dt = times[i + 1] - times[i];
+        let s = (time - times[i]) / dt;
+        Some(match self {
+            Self::Linear => values[i] + (values[i + 1] - values[i]) * s,
+            Self::CatmullRom => {
+                // the slope at each key heads from the key before it to the
+                // one after, with the ends heading into their only neighbour
+                let slope = |k: usize| {
+                    let (a, b) = (k.saturating_sub(1), (k + 1).min(last));
+                    (values[b] - values[a]) * (1.0 / (times[b] - times[a]))
+                };
+                // cubic Hermite basis
+                let s2 = s * s;
+                let s3 = s2 * s;
+                values[i] * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + slope(i) * ((s3 - 2.0 * s2 + s) * dt)
+                    + values[i + 1] * (3.0 * s2 - 2.0 * s3)
+                    + slope(i + 1) * ((s3 - s2) * dt)
+            }
+        })
+    }
+}
+
+// Where the camera is and what it looks at a moment in time, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraKey {
+    pub time: f64,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    // vertical field of view in degrees
+    pub vertical_fov: f64,
+    pub focus_dist: f64,
+}
+
+// Keyframes the camera moves through over an animation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedCameraPath")]
+pub struct CameraPath {
+    // in order of time, with no two at the same time
+    keys: Vec<CameraKey>,
+    interpolation: Interpolation,
+}
+
+// the fields of a camera path that are saved, with its keys in any order
+#[derive(Deserialize)]
+struct SavedCameraPath {
+    keys: Vec<CameraKey>,
+    interpolation: Interpolation,
+}
+
+impl TryFrom<SavedCameraPath> for CameraPath {
+    type Error = String;
+
+    fn try_from(mut saved: SavedCameraPath) -> Result<Self, Self::Error> {
+        sort_keys(&mut saved.keys, |k| k.time)?;
+        Ok(Self {
+            keys: saved.keys,
+            interpolation: saved.interpolation,
+        })
+    }
+}
+
+// Sorts keys read from a file by their times, which have to be finite and
+// different from each other for the keys to be interpolated.
+pub(crate) fn sort_keys<K>(keys: &mut [K], time: impl Fn(&K) -> f64) -> Result<(), String> {
+    if keys.iter().any(|k| !time(k).is_finite()) {
+        return Err("key times must be finite".to_string());
+    }
+    keys.sort_by(|a, b| time(a).total_cmp(&time(b)));
+    if keys.windows(2).any(|w| time(&w[0]) == time(&w[1])) {
+        return Err("two keys have the same time".to_string());
+    }
+    Ok(())
+}
+
+impl CameraPath {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    // Circles the camera once around the point it looks at over the given
+    // number of seconds, keeping its height, field of view and focus.
+    pub fn turntable(
+        look_from: &Point3,
+        look_at: &Point3,
+        vertical_fov: f64,
+        focus_dist: f64,
+        seconds: f64,
+    ) -> Self {
+        let offset = *look_from - *look_at;
+        let radius = offset.x().hypot(offset.z());
+        let start = offset.z().atan2(offset.x());
+        // enough keys for the curve between them to stay close to a circle
+        let steps = 16;
+        let mut path = Self::new(Interpolation::CatmullRom);
+        for k in 0..=steps {
+            let angle = start + 2.0 * PI * k as f64 / steps as f64;
+            path = path.with_key(CameraKey {
+                time: seconds * k as f64 / steps as f64,
+                look_from: *look_at
+                    + Vec3::new(radius * angle.cos(), offset.y(), radius * angle.sin()),
+                look_at: *look_at,
+                vertical_fov,
+                focus_dist,
+            });
+        }
+        path
+    }
+
+    // Keys can be added in any order, and replace any key at the same time.
+    pub fn with_key(mut self, key: CameraKey) -> Self {
+        assert!(key.time.is_finite(), "key times must be finite");
+        let i = self.keys.partition_point(|k| k.time < key.time);
+        match self.keys.get_mut(i) {
+            Some(k) if k.time == key.time => *k = key,
+            _ => self.keys.insert(i, key),
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // time of the last key
+    pub fn duration(&self) -> f64 {
+        self.keys.last().map_or(0.0, |k| k.time)
+    }
+
+    // The camera's placement at a time, or None without any keys.
+    pub fn key_at(&self, time: f64) -> Option<CameraKey> {
+        let times: Vec<f64> = self.keys.iter().map(|k| k.time).collect();
+        let at = |values: Vec<Vec3>| self.interpolation.at(&times, &values, time);
+        let scalar = |values: Vec<f64>| self.interpolation.at(&times, &values, time);
+        Some(CameraKey {
+            time,
+            look_from: at(self.keys.iter().map(|k| k.look_from).collect())?,
+            look_at: at(self.keys.iter().map(|k| k.look_at).collect())?,
+            vertical_fov: scalar(self.keys.iter().map(|k| k.vertical_fov).collect())?,
+            focus_dist: scalar(self.keys.iter().map(|k| k.focus_dist).collect())?,
+        })
+    }
+
+    // The given camera placed as at the given time, keeping its other
+    // settings, or None without any keys.
+    pub fn camera_at(&self, time: f64, camera: &Camera) -> Option<Camera> {
+        self.key_at(time).map(|key| {
+            camera.looking(
+                &key.look_from,
+                &key.look_at,
+                key.vertical_fov,
+                key.focus_dist,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f64, x: f64) -> CameraKey {
+        CameraKey {
+            time,
+            look_from: Point3::new(x, 1.0, 10.0),
+            look_at: Point3::default(),
+            vertical_fov: 20.0 + x,
+            focus_dist: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_linear() {
+        let path = CameraPath::new(Interpolation::Linear)
+            .with_key(key(2.0, 4.0))
+            .with_key(key(0.0, 0.0));
+        assert_eq!(path.duration(), 2.0);
+        let middle = path.key_at(0.5).unwrap();
+        assert!((middle.look_from - Point3::new(1.0, 1.0, 10.0)).len() < 1e-12);
+        assert!((middle.vertical_fov - 21.0).abs() < 1e-12);
+        // holding still outside the keys
+        assert_eq!(path.key_at(-1.0).unwrap().look_from.x(), 0.0);
+        assert_eq!(path.key_at(3.0).unwrap().look_from.x(), 4.0);
+        assert!(CameraPath::default().key_at(0.0).is_none());
+        // a key at the same time replaces the old one
+        let moved = path.with_key(key(2.0, 6.0));
+        assert_eq!(moved.key_at(2.0).unwrap().look_from.x(), 6.0);
+        assert_eq!(moved.keys.len(), 2);
+    }
+
+    #[test]
+    fn test_saved() {
+        let path = CameraPath::new(Interpolation::CatmullRom)
+            .with_key(key(0.0, 0.0))
+            .with_key(key(1.0, 2.0))
+            .with_key(key(2.0, 4.0));
+        let mut json = serde_json::to_value(&path).unwrap();
+        json["keys"].as_array_mut().unwrap().reverse();
+        let loaded: CameraPath = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded, path);
+
+        json["keys"][0]["time"] = serde_json::json!(1.0);
+        let error = serde_json::from_value::<CameraPath>(json).err().unwrap();
+        assert!(error.to_string().contains("same time"));
+    }
+
+    #[test]
+    fn test_catmull_rom() {
+        let times = [0.0, 1.0, 2.0, 3.0];
+        let values = [0.0, 1.0, 0.0, 1.0];
+        let curve = Interpolation::CatmullRom;
+        // passes through the keys
+        for (t, v) in times.iter().zip(values.iter()) {
+            assert!((curve.at(&times, &values, *t).unwrap() - v).abs() < 1e-12);
+        }
+        // with no kink at a key, unlike linear interpolation
+        let slope = |interpolation: Interpolation, t: f64| {
+            let at = |t: f64| interpolation.at(&times, &values, t).unwrap();
+            (at(t + 1e-6) - at(t)) / 1e-6
+        };
+        assert!((slope(curve, 1.0 - 1e-6) - slope(curve, 1.0)).abs() < 1e-3);
+        let linear = Interpolation::Linear;
+        assert!((slope(linear, 1.0 - 1e-6) - slope(linear, 1.0)).abs() > 1.0);
+        // and points on a line stay on it
+        let line = [0.0, 1.0, 2.0, 3.0];
+        assert!((curve.at(&times, &line, 1.3).unwrap() - 1.3).abs() < 1e-12);
+        // nothing to interpolate without keys
+        assert!(curve.at(&[], &[] as &[f64], 0.5).is_none());
+        assert!(curve.at(&[0.0], &[1.0, 2.0], 0.5).is_none());
+        assert_eq!(curve.at(&[1.0], &[2.0], 0.5), Some(2.0));
+    }
+
+    #[test]
+    fn test_turntable() {
+        let look_at = Point3::new(1.0, 0.0, 0.0);
+        let look_from = Point3::new(4.0, 2.0, 4.0);
+        let path = CameraPath::turntable(&look_from, &look_at, 20.0, 3.0, 4.0);
+        assert_eq!(path.duration(), 4.0);
+        for t in [0.0, 0.3, 1.0, 2.7, 4.0] {
+            let key = path.key_at(t).unwrap();
+            assert!((key.focus_dist - 3.0).abs() < 1e-12);
+            let offset = key.look_from - look_at;
+            assert!((offset.x().hypot(offset.z()) - 5.0).abs() < 1e-2);
+            assert!((offset.y() - 2.0).abs() < 1e-12);
+        }
+        // half way round is on the other side
+        let half = path.key_at(2.0).unwrap().look_from;
+        assert!((half - Point3::new(-2.0, 2.0, -4.0)).len() < 1e-9);
+        assert!((path.key_at(4.0).unwrap().look_from - look_from).len() < 1e-9);
+    }
+}
@@ -0,0 +1,1086 @@
+use super::LensSystem;
+use crate::euclidean::{degrees_to_rads, random_in_range, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, HittableList};
+use crate::texture::Image;
+use rand::random;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// attempts at finding a point that gets through the aperture before giving
+// up and using the center of the lens
+const MAX_APERTURE_TRIES: usize = 64;
+
+// Shape of the opening in the lens, which gives out of focus highlights
+// their shape. Every shape fits in the unit circle, scaled by the aperture
+// passed to Camera::new.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    // regular polygon formed by the given number of blades, turned
+    // counter-clockwise by an angle in radians
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+    // image covering the square around the unit circle, letting through
+    // light in proportion to its brightness
+    Mask(Arc<Image>),
+}
+
+impl Aperture {
+    // random point in the aperture in lens coordinates
+    fn sample(&self) -> Option<(f64, f64)> {
+        match self {
+            Self::Circle => {
+                let p = Vec3::random_in_unit_disk();
+                Some((p.x(), p.y()))
+            }
+            Self::Polygon { blades, rotation } => {
+                // uniformly in one of the triangles fanning out from the center
+                let blades = (*blades).max(3) as f64;
+                let corner = |k: f64| {
+                    let angle = rotation + 2.0 * PI * k / blades;
+                    (angle.cos(), angle.sin())
+                };
+                let k = (random::<f64>() * blades).floor();
+                let (a, b) = (corner(k), corner(k + 1.0));
+                let (r1, r2): (f64, f64) = (random(), random());
+                let (wa, wb) = (r1.sqrt() * (1.0 - r2), r1.sqrt() * r2);
+                Some((a.0 * wa + b.0 * wb, a.1 * wa + b.1 * wb))
+            }
+            Self::Mask(image) => (0..MAX_APERTURE_TRIES).find_map(|_| {
+                let (x, y) = (random_in_range(-1.0, 1.0), random_in_range(-1.0, 1.0));
+                let c = image.sample((x + 1.0) / 2.0, (y + 1.0) / 2.0);
+                if random::<f64>() < (c.x() + c.y() + c.z()) / 3.0 {
+                    Some((x, y))
+                } else {
+                    None
+                }
+            }),
+        }
+    }
+}
+
+// How directions from the camera are laid out over the image. Only the
+// perspective projection goes through the lens, the others are always in
+// focus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    // parallel rays along the view direction over a view the given height
+    // in world units, e.g. for elevations of buildings
+    Orthographic {
+        height: f64,
+    },
+    // equidistant fisheye, with the angle from the view direction growing
+    // linearly out to half the field of view in degrees at the corners
+    Fisheye {
+        fov: f64,
+    },
+    // latitude and longitude over the whole sphere around the camera, for
+    // 360 degree panoramas with an aspect ratio of 2
+    Equirectangular,
+    // angle around the up direction across the image, spanning the field of
+    // view in degrees, and height on a unit cylinder up it
+    Cylindrical {
+        fov: f64,
+    },
+}
+
+// Settings of a real camera: the focal length and f-number of its lens and
+// the size of its sensor in millimeters, its sensitivity in ISO and how long
+// the shutter is open in seconds. Scenes are taken to be in meters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraSettings {
+    pub focal_length: f64,
+    pub f_number: f64,
+    pub sensor_width: f64,
+    pub sensor_height: f64,
+    pub iso: f64,
+    pub shutter: f64,
+}
+
+impl Default for CameraSettings {
+    // a 50mm lens at f/8 on a 35mm sensor, exposing for 1/125s at ISO 100
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0,
+            f_number: 8.0,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            iso: 100.0,
+            shutter: 1.0 / 125.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        self.sensor_width / self.sensor_height
+    }
+
+    // in degrees
+    pub fn vertical_fov(&self) -> f64 {
+        2.0 * (self.sensor_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    // diameter of the lens opening in meters
+    pub fn aperture(&self) -> f64 {
+        self.focal_length / self.f_number / 1000.0
+    }
+
+    // Scale from the light in the scene to pixel values. Scene values of 1
+    // stand for daylight, so settings following the sunny 16 rule, f/16 with
+    // the shutter open for 1 / ISO seconds, leave them as they are.
+    pub fn exposure(&self) -> f64 {
+        256.0 * self.shutter * self.iso / (self.f_number * self.f_number)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedCamera")]
+pub struct Camera {
+    origin: Point3,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    // derived from the fields above, so left out of scene files; w points
+    // backwards, away from what the camera looks at
+    #[serde(skip_serializing)]
+    w: Vec3,
+    #[serde(skip_serializing)]
+    aspect_ratio: f64,
+    // kept to aim the camera somewhere else without rolling it
+    view_up: Vec3,
+    lens_radius: f64,
+    aperture: Aperture,
+    cat_eye: f64,
+    projection: Projection,
+    // distance of the eye from the middle of the camera along u, negative
+    // for left eyes, and where the eyes of a stereo pair converge
+    eye_offset: f64,
+    convergence: f64,
+    // lens traced instead of the thin lens for perspective projections, and
+    // the diagonal of the film behind it
+    lens_system: Option<Arc<LensSystem>>,
+    film_diagonal: f64,
+    exposure: f64,
+    // angles in radians the plane in focus is turned by, about u and v
+    tilt: f64,
+    swing: f64,
+}
+
+// the fields of a camera that are saved, with defaults for those added since
+// the first scene files were written
+#[derive(Deserialize)]
+struct SavedCamera {
+    origin: Point3,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    #[serde(default = "y_up")]
+    view_up: Vec3,
+    lens_radius: f64,
+    #[serde(default)]
+    aperture: Aperture,
+    #[serde(default)]
+    cat_eye: f64,
+    #[serde(default)]
+    projection: Projection,
+    #[serde(default)]
+    eye_offset: f64,
+    #[serde(default)]
+    convergence: f64,
+    #[serde(default)]
+    lens_system: Option<Arc<LensSystem>>,
+    #[serde(default)]
+    film_diagonal: f64,
+    #[serde(default = "unit_exposure")]
+    exposure: f64,
+    #[serde(default)]
+    tilt: f64,
+    #[serde(default)]
+    swing: f64,
+}
+
+impl From<SavedCamera> for Camera {
+    fn from(saved: SavedCamera) -> Self {
+        Self {
+            origin: saved.origin,
+            lower_left_corner: saved.lower_left_corner,
+            horizontal: saved.horizontal,
+            vertical: saved.vertical,
+            u: saved.u,
+            v: saved.v,
+            w: saved.u.cross(&saved.v),
+            aspect_ratio: saved.horizontal.len() / saved.vertical.len(),
+            view_up: saved.view_up,
+            lens_radius: saved.lens_radius,
+            aperture: saved.aperture,
+            cat_eye: saved.cat_eye,
+            projection: saved.projection,
+            eye_offset: saved.eye_offset,
+            convergence: saved.convergence,
+            lens_system: saved.lens_system,
+            film_diagonal: saved.film_diagonal,
+            exposure: saved.exposure,
+            tilt: saved.tilt,
+            swing: saved.swing,
+        }
+    }
+}
+
+fn unit_exposure() -> f64 {
+    1.0
+}
+
+fn y_up() -> Vec3 {
+    Vec3::new(0.0, 1.0, 0.0)
+}
+
+impl Camera {
+    pub fn new(
+        look_from: &Point3,
+        look_at: &Point3,
+        view_up: &Vec3,
+        vertical_fov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> Self {
+        let theta = degrees_to_rads(vertical_fov);
+        let h = (theta / 2.0).tan();
+
+        let viewport_height = 2.0 * h;
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let w = (*look_from - *look_at).unit_vector();
+        let u = view_up.cross(&w).unit_vector();
+        let v = w.cross(&u);
+
+        let origin = look_from;
+        let horizontal = u * viewport_width * focus_dist;
+        let vertical = v * viewport_height * focus_dist;
+        let lower_left_corner = *origin - horizontal / 2.0 - vertical / 2.0 - w * focus_dist;
+        let lens_radius = aperture / 2.0;
+        Self {
+            origin: *origin,
+            lower_left_corner,
+            horizontal,
+            vertical,
+            u,
+            v,
+            w,
+            aspect_ratio,
+            view_up: *view_up,
+            lens_radius,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            projection: Projection::Perspective,
+            eye_offset: 0.0,
+            convergence: 0.0,
+            lens_system: None,
+            film_diagonal: 0.0,
+            exposure: 1.0,
+            tilt: 0.0,
+            swing: 0.0,
+        }
+    }
+
+    // A camera with the field of view, depth of field and exposure of real
+    // camera settings, focused the given distance away.
+    pub fn physical(
+        look_from: &Point3,
+        look_at: &Point3,
+        view_up: &Vec3,
+        settings: &CameraSettings,
+        focus_dist: f64,
+    ) -> Self {
+        Self {
+            exposure: settings.exposure(),
+            ..Self::new(
+                look_from,
+                look_at,
+                view_up,
+                settings.vertical_fov(),
+                settings.aspect_ratio(),
+                settings.aperture(),
+                focus_dist,
+            )
+        }
+    }
+
+    // scale to apply to rendered colors before they're written out
+    pub fn exposure(&self) -> f64 {
+        self.exposure
+    }
+
+    // width over height of the images the camera takes
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    pub fn look_from(&self) -> Point3 {
+        self.origin
+    }
+
+    // the middle of the view at the focus distance
+    pub fn look_at(&self) -> Point3 {
+        self.origin - self.w * self.focus_dist()
+    }
+
+    // in degrees, as given to new
+    pub fn vertical_fov(&self) -> f64 {
+        2.0 * (self.vertical.len() / (2.0 * self.focus_dist()))
+            .atan()
+            .to_degrees()
+    }
+
+    // distance in front of the camera to the plane in focus
+    pub fn focus_dist(&self) -> f64 {
+        -(self.lower_left_corner - self.origin).dot(&self.w)
+    }
+
+    // Takes pictures through a lens system onto film with the given diagonal,
+    // e.g. 0.0433 for 35mm film in a scene in meters, with the camera's
+    // origin at the film. The lens decides the field of view and the focus,
+    // so those given to new are ignored.
+    pub fn with_lens_system(mut self, lens_system: LensSystem, film_diagonal: f64) -> Self {
+        self.lens_system = Some(Arc::new(lens_system));
+        self.film_diagonal = film_diagonal;
+        self
+    }
+
+    // Lens shift, moving the view across the image plane by fractions of the
+    // image's width and height without turning the camera, so lines parallel
+    // to the image plane stay parallel, e.g. the verticals of a building
+    // looked up at.
+    pub fn with_shift(mut self, x: f64, y: f64) -> Self {
+        self.lower_left_corner += self.horizontal * x + self.vertical * y;
+        self
+    }
+
+    // Lens tilt, turning the plane in focus by the given angles in degrees
+    // about the horizontal and vertical of the view. A positive tilt leans
+    // the top of the plane away from the camera, so a tilt down towards the
+    // ground keeps a whole floor sharp, and a positive swing does the same
+    // for the right.
+    pub fn with_tilt(mut self, tilt: f64, swing: f64) -> Self {
+        self.tilt = degrees_to_rads(tilt);
+        self.swing = degrees_to_rads(swing);
+        self
+    }
+
+    // Refocuses the given distance in front of the camera, keeping its field
+    // of view and any shift.
+    pub fn with_focus_dist(mut self, focus_dist: f64) -> Self {
+        let scale = focus_dist / self.focus_dist();
+        self.lower_left_corner = self.origin + (self.lower_left_corner - self.origin) * scale;
+        self.horizontal *= scale;
+        self.vertical *= scale;
+        if let Some(lens) = &self.lens_system {
+            if let Some(lens) = lens.as_ref().clone().focused(focus_dist) {
+                self.lens_system = Some(Arc::new(lens));
+            }
+        }
+        self
+    }
+
+    // The camera moved to look from and at other points, with another field
+    // of view and focus, keeping everything else about it such as its
+    // projection, lens, exposure, shift and tilt.
+    pub fn looking(
+        &self,
+        look_from: &Point3,
+        look_at: &Point3,
+        vertical_fov: f64,
+        focus_dist: f64,
+    ) -> Self {
+        // how far the image is shifted from the middle of the view
+        let depth = self.focus_dist();
+        let centered = self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - self.w * depth;
+        let shift = self.lower_left_corner - centered;
+        let aimed = Self::new(
+            look_from,
+            look_at,
+            &self.view_up,
+            vertical_fov,
+            self.aspect_ratio,
+            2.0 * self.lens_radius,
+            focus_dist,
+        );
+        Self {
+            origin: aimed.origin,
+            lower_left_corner: aimed.lower_left_corner,
+            horizontal: aimed.horizontal,
+            vertical: aimed.vertical,
+            u: aimed.u,
+            v: aimed.v,
+            w: aimed.w,
+            ..self.clone()
+        }
+        .with_shift(
+            shift.dot(&self.u) / self.horizontal.len(),
+            shift.dot(&self.v) / self.vertical.len(),
+        )
+        // refocuses any lens system
+        .with_focus_dist(focus_dist)
+    }
+
+    // Focuses on whatever is seen at the image point (s, t), e.g. (0.5, 0.5)
+    // for the middle of the image, at the depth it's hit at in the world. The
+    // focus is left alone if nothing is there.
+    pub fn autofocus(self, world: &HittableList, s: f64, t: f64) -> Self {
+        // through the middle of the lens, so the focus doesn't hit anything
+        // else by chance
+        let ray = match (&self.lens_system, self.projection) {
+            (Some(lens), Projection::Perspective) => {
+                match self.traced_ray(lens, s, t, &Vec3::default()) {
+                    Some(ray) => ray,
+                    None => return self,
+                }
+            }
+            _ => {
+                let (origin, dir) = self.view_ray(s, t);
+                Ray::new(origin, dir)
+            }
+        };
+        let mut record = HitRecord::default();
+        if !world.hit(&ray, 0.001, f64::INFINITY, &mut record) {
+            return self;
+        }
+        // panoramas can see things behind the camera, which can't be focused
+        let depth = -(record.p - self.origin).dot(&self.w);
+        if depth <= 0.0 {
+            return self;
+        }
+        self.with_focus_dist(depth)
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    // Cat-eye vignetting, where the lens barrel clips the aperture of rays
+    // towards the edges of the image so highlights there are cut into
+    // lemon shapes. At a strength of 1 the clipping circle is moved by the
+    // full radius of the aperture at the corners of the image.
+    pub fn with_cat_eye(mut self, strength: f64) -> Self {
+        self.cat_eye = strength.clamp(0.0, 1.0);
+        self
+    }
+
+    // Point on the lens, in multiples of the lens radius, through which the
+    // ray for the image point (s, t) passes.
+    fn lens_point(&self, s: f64, t: f64) -> (f64, f64) {
+        if self.cat_eye == 0.0 {
+            return self.aperture.sample().unwrap_or((0.0, 0.0));
+        }
+        let barrel_x = self.cat_eye * (2.0 * s - 1.0) / 2f64.sqrt();
+        let barrel_y = self.cat_eye * (2.0 * t - 1.0) / 2f64.sqrt();
+        (0..MAX_APERTURE_TRIES)
+            .filter_map(|_| self.aperture.sample())
+            .find(|(x, y)| (x - barrel_x).powi(2) + (y - barrel_y).powi(2) < 1.0)
+            .unwrap_or((0.0, 0.0))
+    }
+
+    // Cameras for the left and right eyes of a stereo pair, the interocular
+    // distance apart and converging at the given distance, where things
+    // appear at the depth of the screen. Panoramic projections become
+    // omnidirectional stereo, with the eyes turning to face each direction.
+    pub fn stereo(&self, interocular: f64, convergence: f64) -> (Self, Self) {
+        assert!(
+            convergence > 0.0,
+            "stereo cameras need to converge in front"
+        );
+        let eye = |offset: f64| Self {
+            eye_offset: offset,
+            convergence,
+            ..self.clone()
+        };
+        (eye(-interocular / 2.0), eye(interocular / 2.0))
+    }
+
+    // The ray for the image point (s, t), or None if it's blocked inside the
+    // lens system. Blocked rays should count as black to get the lens's
+    // vignetting.
+    pub fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        match (&self.lens_system, self.projection) {
+            (Some(lens), Projection::Perspective) => self.lens_system_ray(lens, s, t),
+            _ => Some(self.get_ray(s, t)),
+        }
+    }
+
+    fn lens_system_ray(&self, lens: &LensSystem, s: f64, t: f64) -> Option<Ray> {
+        self.traced_ray(lens, s, t, &Vec3::random_in_unit_disk())
+    }
+
+    // The ray for the image point (s, t) aimed at the given point on the
+    // rear element, in multiples of its radius.
+    fn traced_ray(&self, lens: &LensSystem, s: f64, t: f64, rear: &Vec3) -> Option<Ray> {
+        let height = self.film_diagonal / (1.0 + self.aspect_ratio * self.aspect_ratio).sqrt();
+        let width = height * self.aspect_ratio;
+        // the lens turns the image upside down
+        let film = Point3::new(-(s - 0.5) * width, -(t - 0.5) * height, 0.0);
+        let rear = *rear * lens.rear_radius();
+        let rear = Point3::new(rear.x(), rear.y(), -lens.film_distance());
+        let (p, d) = lens.trace(&film, &(rear - film))?;
+
+        let to_world = |v: Vec3| self.u * v.x() + self.v * v.y() + self.w * v.z();
+        Some(Ray::new(
+            self.origin + self.u * self.eye_offset + to_world(p),
+            to_world(d),
+        ))
+    }
+
+    // Always returns a ray, going straight ahead when the lens system blocks
+    // the ones tried.
+    #[inline(always)]
+    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
+        if let (Some(lens), Projection::Perspective) = (&self.lens_system, self.projection) {
+            return (0..MAX_APERTURE_TRIES)
+                .find_map(|_| self.lens_system_ray(lens, s, t))
+                .unwrap_or_else(|| Ray::new(self.origin, -self.w));
+        }
+        let (origin, dir) = self.view_ray(s, t);
+        match self.projection {
+            Projection::Perspective => {
+                let dir = if self.tilt == 0.0 && self.swing == 0.0 {
+                    dir
+                } else {
+                    self.to_tilted_focus(&origin, &dir, &self.w)
+                };
+                let (x, y) = self.lens_point(s, t);
+                let offset = (self.u * x + self.v * y) * self.lens_radius;
+                Ray::new(origin + offset, dir - offset)
+            }
+            _ => Ray::new(origin, dir),
+        }
+    }
+
+    // The ray for the image point (s, t) from the eye through the middle of
+    // the lens, as laid out by the projection but ignoring any lens system.
+    fn view_ray(&self, s: f64, t: f64) -> (Point3, Vec3) {
+        let (w, aspect_ratio) = (self.w, self.aspect_ratio);
+        let (x, y) = (s - 0.5, t - 0.5);
+        // ray from the middle of the camera, ending on the plane in focus for
+        // perspective projections
+        let (origin, dir) = match self.projection {
+            Projection::Perspective => (
+                self.origin,
+                self.lower_left_corner + self.horizontal * s + self.vertical * t - self.origin,
+            ),
+            Projection::Orthographic { height } => (
+                self.origin + (self.u * x * aspect_ratio + self.v * y) * height,
+                -w,
+            ),
+            Projection::Fisheye { fov } => {
+                let (x, y) = (x * aspect_ratio, y);
+                let r = (x * x + y * y).sqrt();
+                let corner = (aspect_ratio * aspect_ratio + 1.0).sqrt() / 2.0;
+                let theta = degrees_to_rads(fov) / 2.0 * r / corner;
+                let radial = if r > 0.0 {
+                    (self.u * x + self.v * y) / r
+                } else {
+                    Vec3::default()
+                };
+                (self.origin, radial * theta.sin() - w * theta.cos())
+            }
+            Projection::Equirectangular => {
+                let phi = 2.0 * PI * x;
+                let theta = PI * y;
+                let around = self.u * phi.sin() - w * phi.cos();
+                (self.origin, around * theta.cos() + self.v * theta.sin())
+            }
+            Projection::Cylindrical { fov } => {
+                let phi = degrees_to_rads(fov) * x;
+                let height = degrees_to_rads(fov) / aspect_ratio * y;
+                let around = self.u * phi.sin() - w * phi.cos();
+                (self.origin, around + self.v * height)
+            }
+        };
+        if self.eye_offset == 0.0 {
+            (origin, dir)
+        } else {
+            self.eye_ray(&origin, &dir, &w)
+        }
+    }
+
+    // Stretches a perspective ray ending on the untilted plane in focus to
+    // end on the tilted one instead. Rays parallel to the tilted plane are
+    // left alone.
+    fn to_tilted_focus(&self, origin: &Point3, dir: &Vec3, w: &Vec3) -> Vec3 {
+        let depth = -(self.lower_left_corner - self.origin).dot(w);
+        let center = self.origin - *w * depth;
+        let normal = -*w * self.tilt.cos() * self.swing.cos()
+            - self.v * self.tilt.sin()
+            - self.u * self.swing.sin();
+        let t = (center - *origin).dot(&normal) / dir.dot(&normal);
+        if t > 0.0 && t.is_finite() {
+            *dir * t
+        } else {
+            *dir
+        }
+    }
+
+    // Moves a ray from the middle of the camera over to the eye, aiming it at
+    // the point where it crossed the convergence distance.
+    fn eye_ray(&self, origin: &Point3, dir: &Vec3, w: &Vec3) -> (Point3, Vec3) {
+        let side = match self.projection {
+            Projection::Equirectangular | Projection::Cylindrical { .. } => {
+                let side = dir.cross(&self.v);
+                if side.near_zero() {
+                    self.u
+                } else {
+                    side.unit_vector()
+                }
+            }
+            _ => self.u,
+        };
+        let eye = *origin + side * self.eye_offset;
+        match self.projection {
+            // off-axis, so the planes of convergence and focus stay square to
+            // the view direction and the ray still ends on the latter
+            Projection::Perspective => {
+                let depth = -dir.dot(w);
+                let target = *origin + *dir * (self.convergence / depth);
+                (eye, (target - eye) * (depth / self.convergence))
+            }
+            _ => (eye, *origin + dir.unit_vector() * self.convergence - eye),
+        }
+    }
+}
+
+// How the two views of a stereo pair are put together in one image, with
+// the left eye's on the left or on top.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    SideBySide,
+    OverUnder,
+}
+
+impl StereoLayout {
+    // size of the image holding both views, given the size of each
+    pub fn size(&self, width: u64, height: u64) -> (u64, u64) {
+        match self {
+            Self::SideBySide => (2 * width, height),
+            Self::OverUnder => (width, 2 * height),
+        }
+    }
+
+    // The eye whose view holds pixel (i, j) of the combined image, 0 for the
+    // left and 1 for the right, and where the pixel is in that view. Rows are
+    // counted up from the bottom.
+    pub fn locate(&self, i: u64, j: u64, width: u64, height: u64) -> (usize, u64, u64) {
+        match self {
+            Self::SideBySide if i >= width => (1, i - width, j),
+            Self::SideBySide => (0, i, j),
+            Self::OverUnder if j >= height => (0, i, j - height),
+            Self::OverUnder => (1, i, j),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::LensElement;
+    use crate::euclidean::Color;
+    use crate::material::Material;
+    use crate::object::Sphere;
+
+    fn camera(aperture: Aperture) -> Camera {
+        Camera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+            2.0,
+            1.0,
+        )
+        .with_aperture(aperture)
+    }
+
+    #[test]
+    fn test_polygon_aperture() {
+        // a square standing on a corner, so |x| + |y| <= 1
+        let camera = camera(Aperture::Polygon {
+            blades: 4,
+            rotation: 0.0,
+        });
+        let mut beyond_diamond = 0;
+        for _ in 0..1000 {
+            let (x, y) = camera.lens_point(0.5, 0.5);
+            assert!(x.abs() + y.abs() <= 1.0 + 1e-9);
+            if x.abs() + y.abs() > 0.9 {
+                beyond_diamond += 1;
+            }
+        }
+        // some samples reach out towards the edges
+        assert!(beyond_diamond > 0);
+    }
+
+    #[test]
+    fn test_mask_aperture() {
+        // only the right half of the mask is open
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mask = Image::new(2, 1, vec![black, white]);
+        let camera = camera(Aperture::Mask(Arc::new(mask)));
+        for _ in 0..100 {
+            let (x, _) = camera.lens_point(0.5, 0.5);
+            assert!(x >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_orthographic() {
+        let camera =
+            camera(Aperture::Circle).with_projection(Projection::Orthographic { height: 4.0 });
+        let ray = camera.get_ray(1.0, 0.0);
+        assert!((ray.origin - Point3::new(2.0, -2.0, 0.0)).len() < 1e-9);
+        assert_eq!(camera.get_ray(0.3, 0.7).dir, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_fisheye() {
+        let camera = camera(Aperture::Circle).with_projection(Projection::Fisheye { fov: 180.0 });
+        let center = camera.get_ray(0.5, 0.5);
+        assert!((center.dir - Vec3::new(0.0, 0.0, -1.0)).len() < 1e-9);
+        // the corners see out to the side
+        let corner = camera.get_ray(1.0, 1.0).dir.unit_vector();
+        assert!(corner.z().abs() < 1e-9);
+        assert!((corner.x() - corner.y()).abs() < 1e-9 && corner.x() > 0.0);
+    }
+
+    #[test]
+    fn test_equirectangular() {
+        let camera = camera(Aperture::Circle).with_projection(Projection::Equirectangular);
+        let dir = |s, t| camera.get_ray(s, t).dir.unit_vector();
+        assert!((dir(0.5, 0.5) - Vec3::new(0.0, 0.0, -1.0)).len() < 1e-9);
+        assert!((dir(0.75, 0.5) - Vec3::new(1.0, 0.0, 0.0)).len() < 1e-9);
+        assert!((dir(0.0, 0.5) - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-9);
+        assert!((dir(0.3, 1.0) - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-9);
+    }
+
+    #[test]
+    fn test_cylindrical() {
+        let camera =
+            camera(Aperture::Circle).with_projection(Projection::Cylindrical { fov: 180.0 });
+        let dir = camera.get_ray(1.0, 0.5).dir;
+        assert!((dir - Vec3::new(1.0, 0.0, 0.0)).len() < 1e-9);
+        // straight lines up the image stay vertical
+        let top = camera.get_ray(1.0, 1.0).dir;
+        assert!((top.x() - 1.0).abs() < 1e-9 && top.z().abs() < 1e-9);
+        assert!((top.y() - PI / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_saved() {
+        let camera = Camera::new(
+            &Point3::new(1.0, 2.0, 3.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            2.0,
+            0.0,
+            5.0,
+        )
+        .with_projection(Projection::Fisheye { fov: 120.0 });
+        let json = serde_json::to_value(&camera).unwrap();
+        // the derived fields are worked out again when loading
+        assert!(json.get("w").is_none() && json.get("aspect_ratio").is_none());
+        let loaded: Camera = serde_json::from_value(json).unwrap();
+        for (s, t) in [(0.5, 0.5), (0.1, 0.9), (1.0, 0.0)] {
+            let (a, b) = (camera.get_ray(s, t), loaded.get_ray(s, t));
+            assert!((a.origin - b.origin).len() < 1e-12);
+            assert!((a.dir - b.dir).len() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_stereo_converges() {
+        let pinhole = Camera {
+            lens_radius: 0.0,
+            ..camera(Aperture::Circle)
+        };
+        let (left, right) = pinhole.stereo(0.1, 3.0);
+        for (eye, x) in [(&left, -0.05), (&right, 0.05)] {
+            let ray = eye.get_ray(0.5, 0.5);
+            assert!((ray.origin - Point3::new(x, 0.0, 0.0)).len() < 1e-9);
+            // crossing the middle of the view at the convergence distance
+            let at = ray.at(3.0 / -ray.dir.z());
+            assert!((at - Point3::new(0.0, 0.0, -3.0)).len() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_omnidirectional_stereo() {
+        let (left, right) = camera(Aperture::Circle)
+            .with_projection(Projection::Equirectangular)
+            .stereo(0.1, 3.0);
+        // looking along x, the left eye is towards -z
+        let ray = left.get_ray(0.75, 0.5);
+        assert!((ray.origin - Point3::new(0.0, 0.0, -0.05)).len() < 1e-9);
+        let ray = right.get_ray(0.75, 0.5);
+        assert!((ray.origin - Point3::new(0.0, 0.0, 0.05)).len() < 1e-9);
+        assert!((ray.at(1.0) - Point3::new(3.0, 0.0, 0.0)).len() < 1e-9);
+    }
+
+    #[test]
+    fn test_stereo_layout() {
+        let (width, height) = (4, 3);
+        assert_eq!(StereoLayout::SideBySide.size(width, height), (8, 3));
+        assert_eq!(StereoLayout::OverUnder.size(width, height), (4, 6));
+        assert_eq!(
+            StereoLayout::SideBySide.locate(5, 2, width, height),
+            (1, 1, 2)
+        );
+        assert_eq!(
+            StereoLayout::OverUnder.locate(1, 4, width, height),
+            (0, 1, 1)
+        );
+        assert_eq!(
+            StereoLayout::OverUnder.locate(1, 2, width, height),
+            (1, 1, 2)
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let camera = Camera {
+            lens_radius: 0.0,
+            ..camera(Aperture::Circle)
+        }
+        .with_shift(0.0, 0.5);
+        // the middle of the image looks up, but its verticals don't converge
+        assert!((camera.get_ray(0.5, 0.5).dir - Vec3::new(0.0, 1.0, -1.0)).len() < 1e-9);
+        let bottom = camera.get_ray(0.2, 0.1).dir;
+        let top = camera.get_ray(0.2, 0.9).dir;
+        assert!((bottom.x() / bottom.z() - top.x() / top.z()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_looking() {
+        let settings = CameraSettings::default();
+        let camera = Camera {
+            lens_radius: 0.0,
+            ..Camera::physical(
+                &Point3::new(0.0, 1.0, 5.0),
+                &Point3::new(0.0, 1.0, 0.0),
+                &Vec3::new(0.0, 1.0, 0.0),
+                &settings,
+                5.0,
+            )
+        }
+        .with_shift(0.1, 0.2);
+        // aimed where it already was, it takes the same pictures
+        let same = camera.looking(
+            &Point3::new(0.0, 1.0, 5.0),
+            &Point3::new(0.0, 1.0, 0.0),
+            settings.vertical_fov(),
+            5.0,
+        );
+        for (s, t) in [(0.5, 0.5), (0.0, 1.0), (0.7, 0.2)] {
+            let (a, b) = (camera.get_ray(s, t), same.get_ray(s, t));
+            assert!((a.origin - b.origin).len() < 1e-9);
+            assert!((a.dir - b.dir).len() < 1e-9);
+        }
+        // and can be asked where it was aimed
+        assert!((camera.vertical_fov() - settings.vertical_fov()).abs() < 1e-9);
+        assert!((camera.focus_dist() - 5.0).abs() < 1e-9);
+        assert!((camera.look_at() - Point3::new(0.0, 1.0, 0.0)).len() < 1e-9);
+
+        // turned to look along -x, upright and with everything else kept
+        let turned = camera.looking(
+            &Point3::new(5.0, 1.0, 0.0),
+            &Point3::new(0.0, 1.0, 0.0),
+            settings.vertical_fov(),
+            5.0,
+        );
+        assert_eq!(turned.exposure(), settings.exposure());
+        assert!((turned.v - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-12);
+        let middle = camera.get_ray(0.5, 0.5).dir;
+        let turned_middle = turned.get_ray(0.5, 0.5).dir;
+        assert!((turned_middle.y() - middle.y()).abs() < 1e-9);
+        assert!((turned_middle.x() - middle.z()).abs() < 1e-9);
+
+        let fisheye = Projection::Fisheye { fov: 180.0 };
+        let turned = camera.with_projection(fisheye).looking(
+            &Point3::new(5.0, 1.0, 0.0),
+            &Point3::new(0.0, 1.0, 0.0),
+            20.0,
+            5.0,
+        );
+        assert_eq!(turned.projection, fisheye);
+    }
+
+    #[test]
+    fn test_autofocus() {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -5.0),
+            1.0,
+            Material::default(),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(2.0, 0.0, -4.0),
+            1.0,
+            Material::default(),
+        )));
+        let focus = |camera: &Camera, s: f64, t: f64| {
+            let ray = camera.get_ray(s, t);
+            ray.origin + ray.dir
+        };
+        let camera = camera(Aperture::Circle).autofocus(&world, 0.5, 0.5);
+        // the front of the sphere in the middle, wherever the rays go
+        // through the lens
+        assert!((focus(&camera, 0.5, 0.5) - Point3::new(0.0, 0.0, -4.0)).len() < 1e-9);
+        assert!((focus(&camera, 0.5, 0.5) - focus(&camera, 0.5, 0.5)).len() < 1e-9);
+        // with the same field of view
+        assert!((focus(&camera, 1.0, 0.5) - Point3::new(4.0, 0.0, -4.0)).len() < 1e-9);
+
+        // the other sphere seen towards the right, at its depth
+        let camera = camera.autofocus(&world, 0.75, 0.5);
+        let depth = -focus(&camera, 0.75, 0.5).z();
+        assert!((depth - (10.0 - 5f64.sqrt()) / 2.5).abs() < 1e-9);
+        // nothing at the top of the image to focus on
+        let camera = camera.autofocus(&world, 0.5, 1.0);
+        assert!((-focus(&camera, 0.75, 0.5).z() - depth).abs() < 1e-9);
+
+        // picking through a fisheye, which sees further out to the side than
+        // the perspective view would
+        let fisheye = camera.with_projection(Projection::Fisheye { fov: 180.0 });
+        let dir = fisheye.get_ray(1.0, 0.5).dir.unit_vector();
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere::new(dir * 6.0, 1.0, Material::default())));
+        let focused = fisheye.autofocus(&world, 1.0, 0.5);
+        let focus_dist = -(focused.lower_left_corner - focused.origin).dot(&focused.w);
+        assert!((focus_dist - 5.0 * -dir.z()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tilt() {
+        let camera = camera(Aperture::Circle).with_tilt(30.0, 0.0);
+        let focus = |s: f64, t: f64| {
+            let ray = camera.get_ray(s, t);
+            ray.origin + ray.dir
+        };
+        // the middle of the view is still in focus at the focus distance
+        assert!((focus(0.5, 0.5) - Point3::new(0.0, 0.0, -1.0)).len() < 1e-9);
+        // but above it the focus is further away, and below it closer
+        let top = focus(0.5, 0.75);
+        // whichever part of the lens the rays go through
+        assert!((top - focus(0.5, 0.75)).len() < 1e-9);
+        assert!(top.z() < -1.0);
+        assert!(focus(0.5, 0.25).z() > -1.0);
+        // on a plane leaning back by the tilt
+        assert!((top.y() / -(top.z() + 1.0) - 1.0 / 30f64.to_radians().tan()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_physical() {
+        let settings = CameraSettings::default();
+        assert!((settings.vertical_fov() - 26.99).abs() < 0.01);
+        assert_eq!(settings.aspect_ratio(), 1.5);
+        assert_eq!(settings.aperture(), 0.00625);
+
+        let sunny_16 = CameraSettings {
+            f_number: 16.0,
+            iso: 200.0,
+            shutter: 1.0 / 200.0,
+            ..settings
+        };
+        assert!((sunny_16.exposure() - 1.0).abs() < 1e-12);
+        // a stop more light from opening the aperture or the shutter
+        let wider = CameraSettings {
+            f_number: 16.0 / 2f64.sqrt(),
+            ..sunny_16
+        };
+        let longer = CameraSettings {
+            shutter: 1.0 / 100.0,
+            ..sunny_16
+        };
+        assert!((wider.exposure() - 2.0).abs() < 1e-12);
+        assert!((longer.exposure() - 2.0).abs() < 1e-12);
+
+        let camera = Camera::physical(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            &sunny_16,
+            5.0,
+        );
+        assert_eq!(camera.exposure(), sunny_16.exposure());
+        assert_eq!(camera.lens_radius, sunny_16.aperture() / 2.0);
+    }
+
+    #[test]
+    fn test_lens_system() {
+        let lens = LensSystem::new(vec![
+            LensElement {
+                radius: 50.0,
+                thickness: 5.0,
+                ior: 1.5,
+                aperture: 4.0,
+            },
+            LensElement {
+                radius: -50.0,
+                thickness: 0.0,
+                ior: 0.0,
+                aperture: 4.0,
+            },
+        ])
+        .focused(1000.0)
+        .unwrap();
+        let camera = camera(Aperture::Circle).with_lens_system(lens, 40.0);
+        // rays through the middle of the image leave the front of the narrow
+        // lens heading for the focus
+        let rays: Vec<Ray> = (0..100)
+            .filter_map(|_| camera.try_get_ray(0.5, 0.5))
+            .collect();
+        assert!(!rays.is_empty());
+        for ray in rays {
+            let at = ray.at((ray.origin.z() + 1000.0) / -ray.dir.z());
+            assert!(at.x().abs() < 1.0 && at.y().abs() < 1.0);
+        }
+        // and the top right of the image looks up and to the right
+        let ray = camera.get_ray(1.0, 1.0);
+        assert!(ray.dir.x() > 0.0 && ray.dir.y() > 0.0);
+    }
+
+    #[test]
+    fn test_cat_eye() {
+        let camera = camera(Aperture::Circle).with_cat_eye(1.0);
+        let barrel = 1.0 / 2f64.sqrt();
+        for _ in 0..1000 {
+            // at the top right corner the aperture is cut by the barrel
+            let (x, y) = camera.lens_point(1.0, 1.0);
+            assert!(x * x + y * y < 1.0);
+            assert!((x - barrel).powi(2) + (y - barrel).powi(2) < 1.0);
+        }
+        // while rays through the center of the image are not
+        let (x, y) = (0..1000)
+            .map(|_| camera.lens_point(0.5, 0.5))
+            .fold((0.0, 0.0), |(a, b), (x, y)| {
+                (a + x / 1000.0, b + y / 1000.0)
+            });
+        assert!(x.abs() < 0.1 && y.abs() < 0.1);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::euclidean::{Point3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// One spherical interface of a lens system, or the aperture stop when the
+// radius is zero.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LensElement {
+    // radius of curvature, positive when the center is on the film side
+    pub radius: f64,
+    // distance along the axis to the next interface towards the film
+    pub thickness: f64,
+    // index of refraction between this interface and the next, 0 for air
+    pub ior: f64,
+    // diameter of the opening
+    pub aperture: f64,
+}
+
+// A sequence of lens elements in front of the film, traced ray by ray so the
+// lens gives the image its distortion, vignetting and focus breathing.
+//
+// The lens works in its own frame with the film at the origin facing -z,
+// where the scene is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedLensSystem")]
+pub struct LensSystem {
+    // from the front of the lens to the back
+    elements: Vec<LensElement>,
+    // from the rear interface to the film
+    film_distance: f64,
+}
+
+// a lens system as written to scene files, checked before it's used
+#[derive(Deserialize)]
+struct SavedLensSystem {
+    elements: Vec<LensElement>,
+    film_distance: f64,
+}
+
+impl TryFrom<SavedLensSystem> for LensSystem {
+    type Error = String;
+
+    fn try_from(saved: SavedLensSystem) -> Result<Self, Self::Error> {
+        if saved.elements.is_empty() {
+            return Err("lens system has no elements".to_string());
+        }
+        Ok(Self {
+            elements: saved.elements,
+            film_distance: saved.film_distance,
+        })
+    }
+}
+
+impl LensSystem {
+    // The elements are listed from the front of the lens to the back, and
+    // there has to be at least one. The film sits the rear element's
+    // thickness behind it until focused.
+    pub fn new(elements: Vec<LensElement>) -> Self {
+        assert!(!elements.is_empty(), "lens systems need an element");
+        let film_distance = elements.last().map_or(0.0, |e| e.thickness);
+        Self {
+            elements,
+            film_distance,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, scale: f64) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?, scale)
+    }
+
+    // Reads a lens prescription in the format used by pbrt: a line per
+    // interface from the front of the lens to the back, with its radius,
+    // thickness, index of refraction and aperture diameter, and comments
+    // starting with #. Lengths are multiplied by scale, e.g. 0.001 to go from
+    // the usual millimeters to a scene in meters.
+    pub fn parse(text: &str, scale: f64) -> io::Result<Self> {
+        let mut elements = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(|w| w.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| invalid("invalid number in lens prescription"))?;
+            match values.as_slice() {
+                [radius, thickness, ior, aperture] => elements.push(LensElement {
+                    radius: radius * scale,
+                    thickness: thickness * scale,
+                    ior: *ior,
+                    aperture: aperture * scale,
+                }),
+                _ => return Err(invalid("lens elements need four values")),
+            }
+        }
+        if elements.is_empty() {
+            return Err(invalid("lens prescription has no elements"));
+        }
+        Ok(Self::new(elements))
+    }
+
+    pub fn film_distance(&self) -> f64 {
+        self.film_distance
+    }
+
+    // radius of the opening of the rear element, which rays from the film
+    // are aimed through
+    pub fn rear_radius(&self) -> f64 {
+        self.elements.last().map_or(0.0, |e| e.aperture / 2.0)
+    }
+
+    // Moves the film so points the given distance in front of it are in
+    // focus. None if the lens can't focus that close.
+    pub fn focused(self, distance: f64) -> Option<Self> {
+        // how far in front of the film a ray leaving its middle close to the
+        // axis crosses the axis again, infinite for rays that never do
+        let crossing = |film_distance: f64| {
+            let lens = Self {
+                film_distance,
+                ..self.clone()
+            };
+            let height = lens.rear_radius() * 0.01;
+            let (p, d) =
+                match lens.trace(&Point3::default(), &Vec3::new(height, 0.0, -film_distance)) {
+                    Some(ray) => ray,
+                    None => return f64::INFINITY,
+                };
+            if p.x() * d.x() >= 0.0 {
+                return f64::INFINITY;
+            }
+            -(p.z() - p.x() / d.x() * d.z())
+        };
+
+        // the further back the film, the closer the focus
+        let mut near = 0.0;
+        let mut far = self.elements.iter().map(|e| e.thickness).sum::<f64>();
+        for _ in 0..64 {
+            if crossing(far) <= distance {
+                break;
+            }
+            near = far;
+            far *= 2.0;
+        }
+        if crossing(far) > distance {
+            return None;
+        }
+        for _ in 0..100 {
+            let mid = (near + far) / 2.0;
+            if crossing(mid) > distance {
+                near = mid;
+            } else {
+                far = mid;
+            }
+        }
+        Some(Self {
+            film_distance: (near + far) / 2.0,
+            ..self
+        })
+    }
+
+    // Follows a ray from a point on the film out through the lens, returning
+    // where it leaves the front element and its direction then. None if the
+    // ray is blocked by the edge of an element or reflected back inside.
+    pub fn trace(&self, origin: &Point3, dir: &Vec3) -> Option<(Point3, Vec3)> {
+        let mut p = *origin;
+        let mut d = dir.unit_vector();
+        // the vertex of each interface on the axis, from the back
+        let mut z = -self.film_distance;
+        let last = self.elements.len() - 1;
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            if i != last {
+                z -= element.thickness;
+            }
+            if element.radius == 0.0 {
+                p += d * ((z - p.z()) / d.z());
+            } else {
+                // the side of the sphere the vertex is on
+                let center = Point3::new(0.0, 0.0, z + element.radius);
+                let oc = p - center;
+                let half_b = oc.dot(&d);
+                let c = oc.len_squared() - element.radius * element.radius;
+                let discriminant = half_b * half_b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                let t = [-half_b - root, -half_b + root]
+                    .iter()
+                    .copied()
+                    .find(|t| *t > 0.0 && (p + d * *t - center).z() * -element.radius > 0.0)?;
+                p += d * t;
+            }
+            if p.x() * p.x() + p.y() * p.y() > element.aperture * element.aperture / 4.0 {
+                return None;
+            }
+            if element.radius == 0.0 {
+                continue;
+            }
+
+            let mut normal = (p - Point3::new(0.0, 0.0, z + element.radius)).unit_vector();
+            if normal.dot(&d) > 0.0 {
+                normal = -normal;
+            }
+            let ior = |e: &LensElement| if e.ior == 0.0 { 1.0 } else { e.ior };
+            let outside = if i == 0 {
+                1.0
+            } else {
+                ior(&self.elements[i - 1])
+            };
+            let eta_ratio = ior(element) / outside;
+            let cos_theta = -d.dot(&normal);
+            if eta_ratio * eta_ratio * (1.0 - cos_theta * cos_theta) > 1.0 {
+                return None;
+            }
+            d = d.refract(&normal, eta_ratio).unit_vector();
+        }
+        Some((p, d))
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // double Gauss lens from pbrt's scenes, 50mm f/2
+    const DGAUSS: &str = "# D-GAUSS F/2 22deg HFOV
+        # radius thickness ior aperture
+        29.475  3.76   1.67   25.2
+        84.83   0.12   1      25.2
+        19.275  4.025  1.67   23
+        40.77   3.275  1.699  23
+        12.75   5.705  1      18
+        0       4.5    0      17.1
+        -14.495 1.18   1.603  17
+        40.77   6.065  1.658  20
+        -20.385 0.19   1      20
+        437.065 3.22   1.717  20
+        -39.73  0      1      20
+    ";
+
+    #[test]
+    fn test_focal_length() {
+        let lens = LensSystem::parse(DGAUSS, 1.0)
+            .unwrap()
+            .focused(1e9)
+            .unwrap();
+        // a ray from the middle of the film leaves parallel to the axis
+        let (_, d) = lens
+            .trace(
+                &Point3::default(),
+                &Vec3::new(0.1, 0.0, -lens.film_distance()),
+            )
+            .unwrap();
+        assert!(d.x().abs() < 1e-6);
+        // and rays from 1mm off the axis leave at an angle set by the focal
+        // length, nominally 50mm
+        let (_, d) = lens
+            .trace(
+                &Point3::new(1.0, 0.0, 0.0),
+                &Vec3::new(-1.0, 0.0, -lens.film_distance()),
+            )
+            .unwrap();
+        let focal_length = -d.z() / d.x().abs();
+        assert!((focal_length - 50.0).abs() < 2.5);
+    }
+
+    #[test]
+    fn test_focus_breathing() {
+        let lens = LensSystem::parse(DGAUSS, 1.0).unwrap();
+        let far = lens.clone().focused(1e5).unwrap();
+        let near = lens.clone().focused(500.0).unwrap();
+        // focusing closer moves the film away from the lens
+        assert!(near.film_distance() > far.film_distance());
+        assert!(lens.focused(10.0).is_none());
+    }
+
+    #[test]
+    fn test_vignetting() {
+        let lens = LensSystem::parse(DGAUSS, 1.0)
+            .unwrap()
+            .focused(1e4)
+            .unwrap();
+        let through = |x: f64| {
+            let mut count = 0;
+            for i in 0..40 {
+                for j in 0..40 {
+                    let rx = (i as f64 / 39.0 * 2.0 - 1.0) * lens.rear_radius();
+                    let ry = (j as f64 / 39.0 * 2.0 - 1.0) * lens.rear_radius();
+                    let film = Point3::new(x, 0.0, 0.0);
+                    let rear = Point3::new(rx, ry, -lens.film_distance());
+                    if lens.trace(&film, &(rear - film)).is_some() {
+                        count += 1;
+                    }
+                }
+            }
+            count
+        };
+        // less light gets through to the edges of a 35mm frame
+        assert!(through(18.0) < through(0.0));
+        assert!(through(0.0) > 0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(LensSystem::parse("1 2 3", 1.0).is_err());
+        assert!(LensSystem::parse("# nothing", 1.0).is_err());
+        assert!(LensSystem::parse("1 2 x 4", 1.0).is_err());
+        let empty = r#"{"elements": [], "film_distance": 0.05}"#;
+        assert!(serde_json::from_str::<LensSystem>(empty).is_err());
+        let lens = LensSystem::parse("0 0.05 0 0.01", 1.0).unwrap();
+        let json = serde_json::to_string(&lens).unwrap();
+        assert_eq!(serde_json::from_str::<LensSystem>(&json).unwrap(), lens);
+    }
+}
@@ -0,0 +1,9 @@
+mod animation;
+#[allow(clippy::module_inception)]
+mod camera;
+mod lens;
+
+pub(crate) use animation::sort_keys;
+pub use animation::{CameraKey, CameraPath, Interpolation};
+pub use camera::{Aperture, Camera, CameraSettings, Projection, StereoLayout};
+pub use lens::{LensElement, LensSystem};
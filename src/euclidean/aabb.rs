@@ -0,0 +1,107 @@
+use super::{Point3, Ray, Vec3};
+
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        Self::new(
+            Point3::new(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            Point3::new(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+        )
+    }
+
+    // box around a disk with the given center, unit normal and radius
+    pub fn around_disk(center: &Point3, normal: &Vec3, radius: f64) -> Aabb {
+        let extent = Vec3::new(
+            radius * (1.0 - normal.x() * normal.x()).max(0.0).sqrt(),
+            radius * (1.0 - normal.y() * normal.y()).max(0.0).sqrt(),
+            radius * (1.0 - normal.z() * normal.z()).max(0.0).sqrt(),
+        );
+        Self::new(*center - extent, *center + extent)
+    }
+
+    pub fn pad(&self, amount: f64) -> Aabb {
+        let delta = Vec3::new(amount, amount, amount);
+        Self::new(self.min - delta, self.max + delta)
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) / 2.0
+    }
+
+    // Returns the parametric interval over which the ray is inside the box,
+    // clipped to [t_min, t_max].
+    pub fn hit_interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.dir[axis];
+            let mut near = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut far = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // written so that NaNs from rays lying in a slab plane are ignored
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+            if t1 < t0 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
+    #[inline(always)]
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_interval(ray, t_min, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_orders_corners() {
+        let b = Aabb::new(Point3::new(1.0, -1.0, 2.0), Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(b.min, Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(b.max, Point3::new(1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn test_hit() {
+        let b = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(b.hit_interval(&ray, 0.0, f64::INFINITY), Some((4.0, 6.0)));
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!b.hit(&ray, 0.0, f64::INFINITY));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!b.hit(&ray, 0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_around_disk() {
+        let b = Aabb::around_disk(&Point3::default(), &Vec3::new(0.0, 1.0, 0.0), 2.0);
+        assert_eq!(b.min, Point3::new(-2.0, 0.0, -2.0));
+        assert_eq!(b.max, Point3::new(2.0, 0.0, 2.0));
+    }
+}
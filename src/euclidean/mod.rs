@@ -1,7 +1,14 @@
+mod aabb;
+mod onb;
 mod ray;
+pub mod roots;
+mod transform;
 mod util;
 mod vec3;
 
+pub use aabb::Aabb;
+pub use onb::Onb;
 pub use ray::Ray;
+pub use transform::Transform;
 pub use util::{clamp, degrees_to_rads, random_in_range};
 pub use vec3::{Color, Point3, Vec3};
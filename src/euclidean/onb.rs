@@ -0,0 +1,35 @@
+use super::Vec3;
+
+// right handed orthonormal basis
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(w: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).unit_vector();
+        let u = v.cross(&w);
+        Self { u, v, w }
+    }
+
+    // from local coordinates in this basis to world coordinates
+    #[inline(always)]
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        self.u * a.x() + self.v * a.y() + self.w * a.z()
+    }
+
+    // from world coordinates to local coordinates in this basis
+    #[inline(always)]
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
@@ -4,11 +4,22 @@ use super::{Point3, Vec3};
 pub struct Ray {
     pub origin: Point3,
     pub dir: Vec3,
+    // wavelength in nanometers carried by this path in spectral mode
+    pub wavelength: Option<f64>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, dir: Vec3) -> Self {
-        Ray { origin, dir }
+        Ray {
+            origin,
+            dir,
+            wavelength: None,
+        }
+    }
+
+    pub fn with_wavelength(mut self, wavelength: Option<f64>) -> Self {
+        self.wavelength = wavelength;
+        self
     }
 
     pub fn at(&self, t: f64) -> Point3 {
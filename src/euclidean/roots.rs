@@ -0,0 +1,193 @@
+// Closed form polynomial root finding after Jochen Schwarze, "Cubic and
+// Quartic Roots" (Graphics Gems, 1990). Coefficients are given lowest order
+// first and only real roots are returned, in no particular order.
+
+use std::f64::consts::PI;
+
+const EQN_EPSILON: f64 = 1e-9;
+
+#[inline(always)]
+fn is_zero(x: f64) -> bool {
+    x > -EQN_EPSILON && x < EQN_EPSILON
+}
+
+// c[0] + c[1] x + c[2] x² = 0
+pub fn solve_quadratic(c: [f64; 3]) -> Vec<f64> {
+    if c[2] == 0.0 {
+        return if c[1] == 0.0 {
+            vec![]
+        } else {
+            vec![-c[0] / c[1]]
+        };
+    }
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    let d = p * p - q;
+    if is_zero(d) {
+        vec![-p]
+    } else if d < 0.0 {
+        vec![]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![sqrt_d - p, -sqrt_d - p]
+    }
+}
+
+// c[0] + c[1] x + c[2] x² + c[3] x³ = 0
+pub fn solve_cubic(c: [f64; 4]) -> Vec<f64> {
+    if c[3] == 0.0 {
+        return solve_quadratic([c[0], c[1], c[2]]);
+    }
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let c = c[0] / c[3];
+
+    // substitute x = y - a/3 to eliminate the quadratic term: y³ + 3py + 2q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+    let mut roots = if is_zero(d) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        // three real roots
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    for root in roots.iter_mut() {
+        *root -= a / 3.0;
+    }
+    roots
+}
+
+// c[0] + c[1] x + c[2] x² + c[3] x³ + c[4] x⁴ = 0
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    if c[4] == 0.0 {
+        return solve_cubic([c[0], c[1], c[2], c[3]]);
+    }
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+
+    // substitute x = y - a/4 to eliminate the cubic term: y⁴ + py² + qy + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + cc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * cc / 4.0 + d;
+
+    let mut roots = if is_zero(r) {
+        // no absolute term: y(y³ + py + q) = 0
+        let mut roots = solve_cubic([q, p, 0.0, 1.0]);
+        roots.push(0.0);
+        roots
+    } else {
+        // take one root of the resolvent cubic to split into two quadratics
+        let z = solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0])[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+        let mut roots = solve_quadratic([z - u, if q < 0.0 { -v } else { v }, 1.0]);
+        roots.extend(solve_quadratic([z + u, if q < 0.0 { v } else { -v }, 1.0]));
+        roots
+    };
+
+    for root in roots.iter_mut() {
+        *root -= a / 4.0;
+    }
+    roots
+}
+
+// refines a root estimate with a few steps of Newton's method
+pub fn polish_root(c: &[f64], mut x: f64, iterations: usize) -> f64 {
+    for _ in 0..iterations {
+        let (f, df) = c
+            .iter()
+            .rev()
+            .fold((0.0, 0.0), |(f, df), coeff| (f * x + coeff, df * x + f));
+        if df == 0.0 {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        roots
+    }
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        let roots = sorted(roots);
+        assert_eq!(roots.len(), expected.len(), "{:?}", roots);
+        for (r, e) in roots.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-6, "{:?} != {:?}", roots, expected);
+        }
+    }
+
+    #[test]
+    fn test_quadratic() {
+        // (x - 1)(x - 3)
+        assert_roots(solve_quadratic([3.0, -4.0, 1.0]), &[1.0, 3.0]);
+        assert_roots(solve_quadratic([1.0, 0.0, 1.0]), &[]);
+    }
+
+    #[test]
+    fn test_cubic() {
+        // (x + 1)(x - 2)(x - 5)
+        assert_roots(solve_cubic([10.0, 3.0, -6.0, 1.0]), &[-1.0, 2.0, 5.0]);
+        // (x - 1)(x² + 1)
+        assert_roots(solve_cubic([-1.0, 1.0, -1.0, 1.0]), &[1.0]);
+    }
+
+    #[test]
+    fn test_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic([24.0, -50.0, 35.0, -10.0, 1.0]),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x² + 1)(x² + 2)
+        assert_roots(solve_quartic([2.0, 0.0, 3.0, 0.0, 1.0]), &[]);
+    }
+
+    #[test]
+    fn test_polish_root() {
+        let c = [-2.0, 0.0, 1.0];
+        assert!((polish_root(&c, 1.4, 5) - 2.0f64.sqrt()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,207 @@
+use super::{Point3, Vec3};
+use std::ops::Mul;
+
+// Affine transform as a 4x4 matrix acting on column vectors, so a * b is the
+// transform that applies b first and then a.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: [[f64; 4]; 4],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    // from the sixteen entries of the matrix listed column by column
+    pub fn from_columns(columns: &[f64; 16]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in columns.iter().enumerate() {
+            m[i % 4][i / 4] = *value;
+        }
+        Self { m }
+    }
+
+    pub fn translation(offset: &Vec3) -> Self {
+        let mut t = Self::identity();
+        for i in 0..3 {
+            t.m[i][3] = offset[i];
+        }
+        t
+    }
+
+    pub fn scaling(scale: &Vec3) -> Self {
+        let mut t = Self::identity();
+        for i in 0..3 {
+            t.m[i][i] = scale[i];
+        }
+        t
+    }
+
+    // rotation given by a unit quaternion [x, y, z, w]
+    pub fn rotation(q: &[f64; 4]) -> Self {
+        let [x, y, z, w] = *q;
+        Self {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - z * w),
+                    2.0 * (x * z + y * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y + z * w),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - x * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z - y * w),
+                    2.0 * (y * z + x * w),
+                    1.0 - 2.0 * (x * x + y * y),
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // counter-clockwise rotation by an angle in radians about an axis
+    pub fn rotation_about(axis: &Vec3, angle: f64) -> Self {
+        let axis = axis.unit_vector();
+        let (s, c) = (angle / 2.0).sin_cos();
+        Self::rotation(&[axis.x() * s, axis.y() * s, axis.z() * s, c])
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * p.x() + m[i][1] * p.y() + m[i][2] * p.z() + m[i][3];
+        let w = row(3);
+        Point3::new(row(0), row(1), row(2)) / if w == 0.0 { 1.0 } else { w }
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let row = |i: usize| m[i][0] * v.x() + m[i][1] * v.y() + m[i][2] * v.z();
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    // Normals are carried by the inverse transpose, so call this on the
+    // inverse of the transform the surface went through. The result isn't
+    // normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let column = |j: usize| m[0][j] * n.x() + m[1][j] * n.y() + m[2][j] * n.z();
+        Vec3::new(column(0), column(1), column(2))
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, None if the transform
+    // collapses space and can't be undone
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for k in 0..4 {
+                        a[row][k] -= f * a[col][k];
+                        inv[row][k] -= f * inv[col][k];
+                    }
+                }
+            }
+        }
+        Some(Self { m: inv })
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Self { m }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_compose() {
+        let t = Transform::translation(&Vec3::new(1.0, 2.0, 3.0))
+            * Transform::rotation_about(&Vec3::new(0.0, 0.0, 1.0), PI / 2.0)
+            * Transform::scaling(&Vec3::new(2.0, 2.0, 2.0));
+        let p = t.point(&Point3::new(1.0, 0.0, 0.0));
+        assert!((p - Point3::new(1.0, 4.0, 3.0)).len() < 1e-12);
+        // vectors are not translated
+        let v = t.vector(&Vec3::new(1.0, 0.0, 0.0));
+        assert!((v - Vec3::new(0.0, 2.0, 0.0)).len() < 1e-12);
+    }
+
+    #[test]
+    fn test_normal() {
+        // a plane sloping at 45 degrees, stretched along x
+        let t = Transform::scaling(&Vec3::new(2.0, 1.0, 1.0));
+        let n = t
+            .inverse()
+            .unwrap()
+            .normal(&Vec3::new(1.0, 1.0, 0.0))
+            .unit_vector();
+        let tangent = t.vector(&Vec3::new(1.0, -1.0, 0.0));
+        assert!(n.dot(&tangent).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_from_columns() {
+        let mut columns = [0.0; 16];
+        columns[0] = 1.0;
+        columns[5] = 1.0;
+        columns[10] = 1.0;
+        columns[12] = 5.0;
+        columns[15] = 1.0;
+        let t = Transform::from_columns(&columns);
+        assert_eq!(t, Transform::translation(&Vec3::new(5.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = Transform::translation(&Vec3::new(1.0, -2.0, 0.5))
+            * Transform::rotation(&[0.5, 0.5, 0.5, 0.5])
+            * Transform::scaling(&Vec3::new(1.0, 3.0, 0.5));
+        let p = Point3::new(0.3, -0.7, 2.0);
+        let back = t.inverse().unwrap().point(&t.point(&p));
+        assert!((back - p).len() < 1e-12);
+        assert!(Transform::scaling(&Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+}
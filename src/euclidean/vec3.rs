@@ -1,5 +1,6 @@
 use super::{clamp, random_in_range};
 use rand::random;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
@@ -7,7 +8,8 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign,
 pub type Point3 = Vec3;
 pub type Color = Vec3;
 
-#[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Vec3 {
     e: [f64; 3],
 }
@@ -107,7 +109,7 @@ impl Vec3 {
     pub fn refract(&self, normal: &Vec3, eta_ratio: f64) -> Vec3 {
         let cos_theta = (-*self).dot(normal).min(1.0);
         let out_perp = (*self + *normal * cos_theta) * eta_ratio;
-        let out_par = *normal * -((1.0 - out_perp.len_squared()).abs() as f64).sqrt();
+        let out_par = *normal * -(1.0 - out_perp.len_squared()).abs().sqrt();
         out_perp + out_par
     }
 
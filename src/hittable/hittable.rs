@@ -1,16 +1,29 @@
-use crate::euclidean::{Point3, Ray, Vec3};
+use crate::euclidean::{Aabb, Point3, Ray, Vec3};
 use crate::material::Material;
+use std::borrow::Cow;
 
-#[derive(Default, Clone, Copy)]
-pub struct HitRecord {
+// Objects lend their material to the hits on them, so recording a hit that a
+// closer one later replaces costs no more than its position and normal.
+#[derive(Default, Clone)]
+pub struct HitRecord<'a> {
     pub p: Point3,
     pub normal: Vec3,
-    pub material: Material,
+    pub material: Cow<'a, Material>,
     pub t: f64,
     pub front_face: bool,
+    // surface coordinates for texture lookups
+    pub u: f64,
+    pub v: f64,
+    // unit vector along the direction of increasing u, completing a tangent
+    // frame with the normal
+    pub tangent: Vec3,
+    // world distance moved per unit of u along the tangent and per unit of v
+    // across it, zero where the surface has no such coordinates
+    pub dp_du: f64,
+    pub dp_dv: f64,
 }
 
-impl HitRecord {
+impl HitRecord<'_> {
     #[inline(always)]
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
         self.front_face = ray.dir.dot(outward_normal) < 0.0;
@@ -22,6 +35,30 @@ impl HitRecord {
     }
 }
 
+// Span of a ray inside a closed object, bounded by the hits where the ray
+// enters and exits it. The t of either end may be negative.
+#[derive(Clone)]
+pub struct Interval<'a> {
+    pub enter: HitRecord<'a>,
+    pub exit: HitRecord<'a>,
+}
+
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord) -> bool;
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool;
+
+    // None for unbounded objects such as infinite planes
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    // Every interval along the whole line of the ray over which it is inside
+    // the object, sorted by t. Only closed objects can report these, None is
+    // returned for the rest.
+    fn intervals(&self, _ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        None
+    }
+
+    // The object as it's written to scene files, tagged with its kind. None
+    // for objects that can't be saved.
+    fn to_json(&self) -> Option<serde_json::Value> {
+        None
+    }
 }
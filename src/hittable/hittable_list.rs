@@ -3,6 +3,9 @@ use crate::hittable::{HitRecord, Hittable};
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+// offset past a cut-out surface before searching for the next hit
+const PASS_THROUGH_EPSILON: f64 = 1e-4;
+
 #[derive(Default)]
 pub struct HittableList {
     objects: VecDeque<Arc<dyn Hittable + Sync + Send>>,
@@ -23,7 +26,37 @@ impl HittableList {
         self.objects.push_back(object)
     }
 
-    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord) -> bool {
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Hittable + Sync + Send>> + '_ {
+        self.objects.iter()
+    }
+
+    pub fn hit<'a>(
+        &'a self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        record: &mut HitRecord<'a>,
+    ) -> bool {
+        let mut t_min = t_min;
+        // keep looking past surfaces the ray passes through
+        loop {
+            if !self.closest_hit(ray, t_min, t_max, record) {
+                return false;
+            }
+            if !record.material.passes_through(record) {
+                return true;
+            }
+            t_min = record.t + PASS_THROUGH_EPSILON;
+        }
+    }
+
+    fn closest_hit<'a>(
+        &'a self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        record: &mut HitRecord<'a>,
+    ) -> bool {
         let mut temp_record = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
@@ -32,7 +65,8 @@ impl HittableList {
             if object.hit(ray, t_min, closest_so_far, &mut temp_record) {
                 hit_anything = true;
                 closest_so_far = temp_record.t;
-                *record = temp_record;
+                // the old record becomes scratch space for the next hit
+                std::mem::swap(record, &mut temp_record);
             }
         }
         hit_anything
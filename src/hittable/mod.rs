@@ -1,5 +1,6 @@
+#[allow(clippy::module_inception)]
 mod hittable;
 mod hittable_list;
 
-pub use hittable::{HitRecord, Hittable};
+pub use hittable::{HitRecord, Hittable, Interval};
 pub use hittable_list::HittableList;
@@ -0,0 +1,9 @@
+pub mod camera;
+pub mod euclidean;
+pub mod hittable;
+pub mod material;
+pub mod mesh;
+pub mod object;
+pub mod scene;
+pub mod spectrum;
+pub mod texture;
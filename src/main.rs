@@ -1,17 +1,16 @@
-mod camera;
-mod euclidean;
-mod hittable;
-mod material;
-mod object;
-
-use camera::Camera;
-use euclidean::{random_in_range, Color, Point3, Ray, Vec3};
-use hittable::{HitRecord, HittableList};
 use indicatif::ProgressBar;
-use material::Material;
-use object::Sphere;
 use rand::random;
 use rayon::prelude::*;
+use raytracer::camera::{Camera, CameraPath, CameraSettings, LensSystem, StereoLayout};
+use raytracer::euclidean::{random_in_range, Color, Point3, Ray, Vec3};
+use raytracer::hittable::{HitRecord, HittableList};
+use raytracer::material::Material;
+use raytracer::object::{Heightfield, Sphere};
+use raytracer::scene::{Animation, Frame, Scene};
+use raytracer::spectrum::{sample_wavelength, wavelength_to_rgb, Dispersion};
+use raytracer::texture::{SurfaceMap, Texture};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::sync::Arc;
 
 fn ray_color(ray: &Ray, world: &HittableList, depth: u32) -> Color {
@@ -20,16 +19,19 @@ fn ray_color(ray: &Ray, world: &HittableList, depth: u32) -> Color {
         return Color::new(0.0, 0.0, 0.0);
     }
     let mut record = HitRecord::default();
-    if world.hit(&ray, 0.001, f64::INFINITY, &mut record) {
+    if world.hit(ray, 0.001, f64::INFINITY, &mut record) {
         let mut scattered = Ray::default();
         let mut attenuation = Color::default();
+        let emitted = record.material.emitted(&record);
         if record
             .material
             .scatter(ray, &record, &mut attenuation, &mut scattered)
         {
-            ray_color(&scattered, &world, depth - 1) * attenuation
+            // the whole path shares the wavelength sampled at the camera
+            scattered.wavelength = ray.wavelength;
+            emitted + ray_color(&scattered, world, depth - 1) * attenuation
         } else {
-            Color::new(0.0, 0.0, 0.0)
+            emitted
         }
     } else {
         let unit_direction = ray.dir.unit_vector();
@@ -44,11 +46,22 @@ fn random_scene() -> HittableList {
     let ground_material = Material::Lambertian {
         albedo: Color::new(0.5, 0.5, 0.5),
     };
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
+    // rolling hills around y = 0, reaching far enough to meet the horizon
+    let ground = Arc::new(Heightfield::from_noise(
+        512,
+        512,
+        Point3::new(-200.0, -1.0, -200.0),
+        Vec3::new(400.0, 2.0, 400.0),
+        0.05,
+        6,
         ground_material,
-    )));
+    ));
+    world.add(ground.clone());
+    // rests a sphere with the given center on the ground below it
+    let on_ground = |center: Point3, radius: f64| {
+        let ground_height = ground.height_at(center.x(), center.z()).unwrap_or(0.0);
+        Point3::new(center.x(), ground_height + radius, center.z())
+    };
 
     for a in (0..22).map(|v| v - 11) {
         for b in (0..22).map(|v| v - 11) {
@@ -73,31 +86,48 @@ fn random_scene() -> HittableList {
                 } else {
                     Material::Dialectric { ir: 1.5 }
                 };
-                world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
+                world.add(Arc::new(Sphere::new(
+                    on_ground(center, 0.2),
+                    0.2,
+                    sphere_material,
+                )));
             }
         }
     }
 
-    let material1 = Material::Dialectric { ir: 1.5 };
-    let material2 = Material::Lambertian {
-        albedo: Color::new(0.4, 0.2, 0.1),
+    let material1 = Material::DispersiveDialectric {
+        dispersion: Dispersion::BK7,
     };
-    let material3 = Material::Metal {
-        albedo: Color::new(0.7, 0.6, 0.5),
-        fuzz_in: 0.0,
+    // varnished wood
+    let material2 = Material::Coated {
+        base: Arc::new(Material::Lambertian {
+            albedo: Color::new(0.4, 0.2, 0.1),
+        }),
+        ir: 1.5,
+    };
+    // hammered metal
+    let material3 = Material::Mapped {
+        base: Arc::new(Material::Metal {
+            albedo: Color::new(0.7, 0.6, 0.5),
+            fuzz_in: 0.0,
+        }),
+        map: SurfaceMap::Bump {
+            height: Texture::Noise { scale: 8.0 },
+            strength: 0.02,
+        },
     };
     world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, 1.0, 0.0),
+        on_ground(Point3::new(0.0, 1.0, 0.0), 1.0),
         1.0,
         material1,
     )));
     world.add(Arc::new(Sphere::new(
-        Point3::new(-4.0, 1.0, 0.0),
+        on_ground(Point3::new(-4.0, 1.0, 0.0), 1.0),
         1.0,
         material2,
     )));
     world.add(Arc::new(Sphere::new(
-        Point3::new(4.0, 1.0, 0.0),
+        on_ground(Point3::new(4.0, 1.0, 0.0), 1.0),
         1.0,
         material3,
     )));
@@ -105,52 +135,213 @@ fn random_scene() -> HittableList {
 }
 
 fn main() {
-    // image
-    let aspect_ratio = 16.0 / 9.0;
+    // photograph with the depth of field and exposure of a real camera,
+    // rather than with the field of view and aperture below
+    let camera_settings: Option<CameraSettings> = None;
+
+    // image, as tall as the aspect ratio of the scene's camera makes it
     let image_width: u64 = 200;
-    let image_height: u64 = (image_width as f64 / aspect_ratio) as u64;
     let samples_per_pixel = 50;
     let max_bounce_depth = 50;
-
-    // world
-    let mut world = random_scene();
+    // trace a single wavelength per sample so dispersive materials split light
+    let spectral = false;
+    // render left and right eye views into one image, e.g. for VR headsets,
+    // with the eyes this far apart and converging at this distance
+    let stereo: Option<StereoLayout> = None;
+    let interocular = 0.3;
+    let convergence = 10.0;
 
     // camera
     let look_from = Point3::new(13.0, 2.0, 3.0);
     let look_at = Point3::new(0.0, 0.0, 0.0);
     let view_up = Vec3::new(0.0, 1.0, 0.0);
+    let aspect_ratio = camera_settings.map_or(16.0 / 9.0, |settings| settings.aspect_ratio());
+    let vertical_fov = 20.0;
     let dist_to_focus = 10.0;
+    // focus instead on whatever is seen at the pixel in this column and row
+    // counted from the top left, or at the middle of the image if None
+    let autofocus = false;
+    let focus_pixel: Option<(u64, u64)> = None;
     let aperture = 0.1;
-    let cam = Camera::new(
-        &look_from,
-        &look_at,
-        &view_up,
-        20.0,
-        aspect_ratio,
-        aperture,
-        dist_to_focus,
-    );
+    // trace through the lenses in a prescription in millimeters onto 35mm
+    // film, rather than an ideal thin lens
+    let lens_prescription: Option<&str> = None;
+    let lens = lens_prescription
+        .map(|path| LensSystem::load(path, 0.001).expect("failed to load the lens"));
+    let camera = |look_from: &Point3, look_at: &Point3, vertical_fov: f64, dist_to_focus: f64| {
+        let cam = match camera_settings {
+            Some(settings) => {
+                Camera::physical(look_from, look_at, &view_up, &settings, dist_to_focus)
+            }
+            None => Camera::new(
+                look_from,
+                look_at,
+                &view_up,
+                vertical_fov,
+                aspect_ratio,
+                aperture,
+                dist_to_focus,
+            ),
+        };
+        match &lens {
+            Some(lens) => {
+                let lens = lens
+                    .clone()
+                    .focused(dist_to_focus)
+                    .expect("the lens can't focus that close");
+                cam.with_lens_system(lens, 0.0433)
+            }
+            None => cam,
+        }
+    };
+    // frames of an animation go by at this rate, with the camera circling the
+    // scene unless the scene has a path of its own
+    let frames_per_second = 24.0;
+
+    // world, either a new random one or one saved earlier, with
+    // --save <file> and --load <file> to reproduce a render later, and
+    // --frames <first> <last> to write the frames of its animation to
+    // numbered images; --load also takes glTF and pbrt scenes
+    let mut load = None;
+    let mut save = None;
+    let mut frames = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--load" => load = args.next(),
+            "--save" => save = args.next(),
+            "--frames" => {
+                let mut number = || {
+                    args.next()
+                        .and_then(|n| n.parse::<u32>().ok())
+                        .expect("--frames needs the first and last frame")
+                };
+                let first = number();
+                frames = Some(first..=number());
+            }
+            _ => {}
+        }
+    }
+    let scene = match load {
+        Some(path) => Scene::load(path).expect("failed to load the scene"),
+        None => Scene {
+            world: random_scene(),
+            cameras: vec![camera(&look_from, &look_at, vertical_fov, dist_to_focus)],
+            animation: Animation::default(),
+        },
+    };
+    if let Some(path) = save {
+        scene.save_json(path).expect("failed to save the scene");
+    }
+    let scene_camera = scene.cameras.first().expect("the scene has no camera");
+    let image_height: u64 = (image_width as f64 / scene_camera.aspect_ratio()) as u64;
+
+    let focus = |cam: Camera, world: &HittableList| {
+        if !autofocus {
+            return cam;
+        }
+        let (s, t) = focus_pixel.map_or((0.5, 0.5), |(i, j)| {
+            // pixels past the edges focus on the nearest one inside
+            let (i, j) = (i.min(image_width - 1), j.min(image_height - 1));
+            (
+                i as f64 / (image_width - 1) as f64,
+                (image_height - 1 - j) as f64 / (image_height - 1) as f64,
+            )
+        });
+        cam.autofocus(world, s, t)
+    };
 
     // render
-    let progress_bar = ProgressBar::new(image_height);
-    print!("P3\n{} {}\n255\n", image_width, image_height);
-    for j in (0..image_height).rev() {
-        progress_bar.inc(1);
-        for i in 0..image_width {
-            let pixel_color: Color = (0..samples_per_pixel)
-                .into_par_iter()
-                .map(|_| {
-                    let u = (i as f64 + random::<f64>()) / (image_width - 1) as f64;
-                    let v = (j as f64 + random::<f64>()) / (image_height - 1) as f64;
-                    let ray = cam.get_ray(u, v);
-                    ray_color(&ray, &world, max_bounce_depth)
-                })
-                .reduce(Color::default, |acc, c| acc + c);
-            print!(
-                "{}",
-                pixel_color.as_multisample_color_str(samples_per_pixel)
-            );
+    let (output_width, output_height) = stereo.map_or((image_width, image_height), |layout| {
+        layout.size(image_width, image_height)
+    });
+    let render = |cam: &Camera, world: &HittableList, out: &mut dyn Write| -> io::Result<()> {
+        let views = match stereo {
+            Some(_) => {
+                let (left, right) = cam.stereo(interocular, convergence);
+                vec![left, right]
+            }
+            None => vec![cam.clone()],
+        };
+        let progress_bar = ProgressBar::new(output_height);
+        write!(out, "P3\n{} {}\n255\n", output_width, output_height)?;
+        for j in (0..output_height).rev() {
+            progress_bar.inc(1);
+            for i in 0..output_width {
+                let (eye, i, j) = stereo.map_or((0, i, j), |layout| {
+                    layout.locate(i, j, image_width, image_height)
+                });
+                let cam = &views[eye];
+                let pixel_color: Color = (0..samples_per_pixel)
+                    .into_par_iter()
+                    .map(|_| {
+                        let u = (i as f64 + random::<f64>()) / (image_width - 1) as f64;
+                        let v = (j as f64 + random::<f64>()) / (image_height - 1) as f64;
+                        // rays blocked inside the lens bring no light
+                        let ray = match cam.try_get_ray(u, v) {
+                            Some(ray) => ray,
+                            None => return Color::default(),
+                        };
+                        if spectral {
+                            let wavelength = sample_wavelength();
+                            let ray = ray.with_wavelength(Some(wavelength));
+                            ray_color(&ray, world, max_bounce_depth) * wavelength_to_rgb(wavelength)
+                        } else {
+                            ray_color(&ray, world, max_bounce_depth)
+                        }
+                    })
+                    .reduce(Color::default, |acc, c| acc + c);
+                write!(
+                    out,
+                    "{}",
+                    (pixel_color * cam.exposure()).as_multisample_color_str(samples_per_pixel)
+                )?;
+            }
+        }
+        progress_bar.finish();
+        Ok(())
+    };
+
+    match frames {
+        Some(frames) => {
+            let camera_path = if scene.animation.camera.is_empty() {
+                // around whatever the camera sees in the middle of the view,
+                // or the point it's focused on if nothing is there
+                let center = scene_camera
+                    .clone()
+                    .autofocus(&scene.frame(0.0).world, 0.5, 0.5)
+                    .look_at();
+                CameraPath::turntable(
+                    &scene_camera.look_from(),
+                    &center,
+                    scene_camera.vertical_fov(),
+                    scene_camera.focus_dist(),
+                    4.0,
+                )
+            } else {
+                scene.animation.camera.clone()
+            };
+            let mut world = Frame::default();
+            for frame in frames {
+                let time = frame as f64 / frames_per_second;
+                let cam = camera_path
+                    .camera_at(time, scene_camera)
+                    .expect("the camera path has no keys");
+                world.update(&scene, time);
+                let cam = focus(cam, &world.world);
+                let path = format!("frame{:04}.ppm", frame);
+                let mut file =
+                    BufWriter::new(File::create(&path).expect("failed to create a frame"));
+                render(&cam, &world.world, &mut file)
+                    .and_then(|_| file.flush())
+                    .expect("failed to write a frame");
+                eprintln!("wrote {}", path);
+            }
+        }
+        None => {
+            let world = scene.frame(0.0).world;
+            let cam = focus(scene_camera.clone(), &world);
+            render(&cam, &world, &mut io::stdout().lock()).expect("failed to write the image");
         }
     }
-    progress_bar.finish();
 }
@@ -1,19 +1,87 @@
 use crate::euclidean::{Color, Ray, Vec3};
 use crate::hittable::HitRecord;
+use crate::spectrum::{Dispersion, LAMBDA_D};
+use crate::texture::{SurfaceMap, Texture};
 use rand::random;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-#[derive(Clone, Copy)]
+// wavelengths in nanometers standing in for the red, green and blue channels
+// when a wavelength dependent material is hit outside of spectral mode
+const RGB_WAVELENGTHS: [f64; 3] = [650.0, 532.0, 450.0];
+
+// index of refraction of keratin, for the cuticle of hair
+const HAIR_IR: f64 = 1.55;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub enum Material {
+    #[default]
     NoMaterial,
-    Lambertian { albedo: Color },
-    Metal { albedo: Color, fuzz_in: f64 },
-    Dialectric { ir: f64 },
-}
-
-impl Default for Material {
-    fn default() -> Self {
-        Self::NoMaterial
-    }
+    Lambertian {
+        albedo: Color,
+    },
+    Metal {
+        albedo: Color,
+        fuzz_in: f64,
+    },
+    Dialectric {
+        ir: f64,
+    },
+    // dielectric whose index of refraction depends on the ray's wavelength
+    DispersiveDialectric {
+        dispersion: Dispersion,
+    },
+    // smooth dielectric clear coat layered over another material
+    Coated {
+        base: Arc<Material>,
+        ir: f64,
+    },
+    // thin dielectric film in air (e.g. a soap bubble), thickness in nanometers
+    ThinFilm {
+        ir: f64,
+        thickness: f64,
+    },
+    // perturbs the shading normal with a normal or bump map before the base
+    // material scatters
+    Mapped {
+        base: Arc<Material>,
+        map: SurfaceMap,
+    },
+    // base material with holes where the opacity texture is below one, e.g.
+    // leaves and fences; the opacity is the chance of a ray hitting the surface
+    Cutout {
+        base: Arc<Material>,
+        opacity: Texture,
+    },
+    // tints what the base material scatters with a texture, e.g. the color
+    // map of an imported model
+    Textured {
+        base: Arc<Material>,
+        albedo: Texture,
+    },
+    // different materials for the front and back of a surface, decided by
+    // which side of the surface the ray hits
+    TwoSided {
+        front: Arc<Material>,
+        back: Arc<Material>,
+    },
+    // culls back faces, letting rays through when they hit the back of a surface
+    SingleSided {
+        base: Arc<Material>,
+    },
+    // area light emitting from the front of its surface without scattering
+    DiffuseLight {
+        emit: Color,
+    },
+    // fiber scattering around the hit's tangent, for curves: an uncolored
+    // reflection off the cuticle, light transmitted through the fiber, and
+    // light reflected off its far side, colored once and twice by the fiber
+    Hair {
+        color: Color,
+        roughness: f64,
+    },
 }
 
 impl Material {
@@ -44,28 +112,156 @@ impl Material {
             }
             Self::Dialectric { ir } => {
                 *attenuation = Color::new(1.0, 1.0, 1.0);
-                *scattered = {
-                    let refraction_ratio = if record.front_face { 1.0 / ir } else { *ir };
-                    let unit_direction = ray_in.dir.unit_vector();
-                    let cos_theta = (-unit_direction).dot(&record.normal).min(1.0);
-                    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-
-                    let cannot_refract = refraction_ratio * sin_theta > 1.0;
-                    let direction = if cannot_refract
-                        || Self::reflectance(cos_theta, refraction_ratio) > random()
-                    {
-                        unit_direction.reflect(&record.normal)
-                    } else {
-                        unit_direction.refract(&record.normal, refraction_ratio)
-                    };
-                    Ray::new(record.p, direction)
+                *scattered = Self::refract_or_reflect(ray_in, record, *ir);
+                true
+            }
+            Self::DispersiveDialectric { dispersion } => {
+                *attenuation = Color::new(1.0, 1.0, 1.0);
+                let ir = dispersion.ior(ray_in.wavelength.unwrap_or(LAMBDA_D));
+                *scattered = Self::refract_or_reflect(ray_in, record, ir);
+                true
+            }
+            Self::Coated { base, ir } => {
+                let unit_direction = ray_in.dir.unit_vector();
+                let cos_theta = (-unit_direction).dot(&record.normal).min(1.0);
+                if record.front_face && Self::reflectance(cos_theta, *ir) > random() {
+                    *attenuation = Color::new(1.0, 1.0, 1.0);
+                    *scattered = Ray::new(record.p, unit_direction.reflect(&record.normal));
+                    true
+                } else {
+                    // light that makes it through the coat interacts with the base
+                    base.scatter(ray_in, record, attenuation, scattered)
+                }
+            }
+            Self::ThinFilm { ir, thickness } => {
+                let unit_direction = ray_in.dir.unit_vector();
+                let cos_theta = (-unit_direction).dot(&record.normal).min(1.0);
+                let reflect = |wavelength| {
+                    Self::thin_film_reflectance(cos_theta, *ir, *thickness, wavelength)
+                };
+                let (reflectance, p_reflect) = match ray_in.wavelength {
+                    Some(wavelength) => {
+                        let r = reflect(wavelength);
+                        (Color::new(r, r, r), r)
+                    }
+                    None => {
+                        let [r, g, b] = RGB_WAVELENGTHS;
+                        let rgb = Color::new(reflect(r), reflect(g), reflect(b));
+                        (rgb, (rgb.x() + rgb.y() + rgb.z()) / 3.0)
+                    }
                 };
+                // the film is thin enough that transmitted light continues undeviated
+                if p_reflect > random() {
+                    *attenuation = reflectance / p_reflect;
+                    *scattered = Ray::new(record.p, unit_direction.reflect(&record.normal));
+                } else {
+                    *attenuation = (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - p_reflect);
+                    *scattered = Ray::new(record.p, unit_direction);
+                }
+                true
+            }
+            Self::Mapped { base, map } => {
+                let mut mapped = record.clone();
+                map.perturb(&mut mapped);
+                base.scatter(ray_in, &mapped, attenuation, scattered)
+            }
+            Self::Cutout { base, opacity: _ } => {
+                base.scatter(ray_in, record, attenuation, scattered)
+            }
+            Self::Textured { base, albedo } => {
+                let scatters = base.scatter(ray_in, record, attenuation, scattered);
+                *attenuation = *attenuation * albedo.value(record.u, record.v, &record.p);
+                scatters
+            }
+            Self::TwoSided { front, back } => {
+                let side = if record.front_face { front } else { back };
+                side.scatter(ray_in, record, attenuation, scattered)
+            }
+            Self::SingleSided { base } => base.scatter(ray_in, record, attenuation, scattered),
+            Self::Hair { color, roughness } => {
+                let unit_direction = ray_in.dir.unit_vector();
+                let cos_theta = (-unit_direction).dot(&record.normal).abs().min(1.0);
+                // the transmitted and internally reflected lobes split what
+                // the cuticle doesn't reflect evenly
+                let (direction, spread) = if Self::reflectance(cos_theta, HAIR_IR) > random() {
+                    *attenuation = Color::new(1.0, 1.0, 1.0);
+                    (unit_direction.reflect(&record.normal), *roughness)
+                } else if random::<f64>() < 0.5 {
+                    *attenuation = *color;
+                    (unit_direction, *roughness)
+                } else {
+                    *attenuation = *color * *color;
+                    (unit_direction.reflect(&record.normal), 2.0 * roughness)
+                };
+                // keep the lobes on cones around the fiber's axis, blurred
+                // across by the roughness
+                let along = record.tangent * direction.dot(&record.tangent);
+                let around = direction - along + Vec3::random_in_unit_sphere() * spread;
+                let around = around - record.tangent * around.dot(&record.tangent);
+                *scattered = Ray::new(
+                    record.p,
+                    along + around.unit_vector() * (direction - along).len(),
+                );
                 true
             }
-            Self::NoMaterial => false,
+            Self::DiffuseLight { emit: _ } | Self::NoMaterial => false,
+        }
+    }
+
+    // light given off by the surface at this hit
+    pub fn emitted(&self, record: &HitRecord) -> Color {
+        match self {
+            Self::DiffuseLight { emit } if record.front_face => *emit,
+            Self::Mapped { base, map: _ }
+            | Self::Textured { base, albedo: _ }
+            | Self::Cutout { base, opacity: _ }
+            | Self::Coated { base, ir: _ }
+            | Self::SingleSided { base } => base.emitted(record),
+            Self::TwoSided { front, back } => {
+                let side = if record.front_face { front } else { back };
+                side.emitted(record)
+            }
+            _ => Color::default(),
         }
     }
 
+    // Decides whether a ray goes through the surface at this hit as if it
+    // wasn't there. Sampled once per hit, so partially opaque surfaces let
+    // through a matching fraction of rays.
+    pub fn passes_through(&self, record: &HitRecord) -> bool {
+        match self {
+            Self::Cutout { base, opacity } => {
+                opacity.intensity(record.u, record.v, &record.p) <= random::<f64>()
+                    || base.passes_through(record)
+            }
+            Self::Mapped { base, map: _ }
+            | Self::Textured { base, albedo: _ }
+            | Self::Coated { base, ir: _ } => base.passes_through(record),
+            Self::TwoSided { front, back } => {
+                let side = if record.front_face { front } else { back };
+                side.passes_through(record)
+            }
+            Self::SingleSided { base } => !record.front_face || base.passes_through(record),
+            _ => false,
+        }
+    }
+
+    fn refract_or_reflect(ray_in: &Ray, record: &HitRecord, ir: f64) -> Ray {
+        let refraction_ratio = if record.front_face { 1.0 / ir } else { ir };
+        let unit_direction = ray_in.dir.unit_vector();
+        let cos_theta = (-unit_direction).dot(&record.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let direction =
+            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random() {
+                unit_direction.reflect(&record.normal)
+            } else {
+                unit_direction.refract(&record.normal, refraction_ratio)
+            };
+        Ray::new(record.p, direction)
+    }
+
     #[inline(always)]
     pub fn fuzz(&self) -> Option<f64> {
         match self {
@@ -86,4 +282,106 @@ impl Material {
         r0 *= r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
     }
+
+    // Reflectance of a film of the given index surrounded by air, from the
+    // interference of the light reflected off its two surfaces. Each surface
+    // reflects according to the Schlick approximation.
+    pub fn thin_film_reflectance(
+        cosine: f64,
+        film_index: f64,
+        thickness: f64,
+        wavelength: f64,
+    ) -> f64 {
+        let r = Self::reflectance(cosine, film_index);
+        let sin_t2 = (1.0 - cosine * cosine) / (film_index * film_index);
+        let cos_t = (1.0 - sin_t2).max(0.0).sqrt();
+        // optical path difference between the two reflections as a phase
+        let delta = 4.0 * PI * film_index * thickness * cos_t / wavelength;
+        let c = delta.cos();
+        2.0 * r * (1.0 - c) / (1.0 + r * r - 2.0 * r * c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thin_film_vanishes_at_zero_thickness() {
+        assert!(Material::thin_film_reflectance(0.8, 1.33, 0.0, 550.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_thin_film_reflectance_bounds() {
+        for thickness in (0..100).map(|t| t as f64 * 10.0) {
+            for wavelength in RGB_WAVELENGTHS.iter() {
+                let r = Material::thin_film_reflectance(0.5, 1.33, thickness, *wavelength);
+                assert!((0.0..=1.0).contains(&r));
+            }
+        }
+    }
+
+    #[test]
+    fn test_thin_film_is_wavelength_dependent() {
+        let r = Material::thin_film_reflectance(1.0, 1.33, 250.0, 650.0);
+        let b = Material::thin_film_reflectance(1.0, 1.33, 250.0, 450.0);
+        assert!((r - b).abs() > 1e-3);
+    }
+
+    #[test]
+    fn test_diffuse_light_emits_from_front() {
+        let light = Material::DiffuseLight {
+            emit: Color::new(4.0, 4.0, 4.0),
+        };
+        let mut record = HitRecord {
+            front_face: true,
+            ..HitRecord::default()
+        };
+        assert_eq!(light.emitted(&record), Color::new(4.0, 4.0, 4.0));
+        record.front_face = false;
+        assert_eq!(light.emitted(&record), Color::default());
+        let mut attenuation = Color::default();
+        let mut scattered = Ray::default();
+        assert!(!light.scatter(&Ray::default(), &record, &mut attenuation, &mut scattered));
+    }
+
+    #[test]
+    fn test_single_sided_culls_back_faces() {
+        let material = Material::SingleSided {
+            base: Arc::new(Material::Lambertian {
+                albedo: Color::new(0.5, 0.5, 0.5),
+            }),
+        };
+        let mut record = HitRecord {
+            front_face: true,
+            ..HitRecord::default()
+        };
+        assert!(!material.passes_through(&record));
+        record.front_face = false;
+        assert!(material.passes_through(&record));
+    }
+
+    #[test]
+    fn test_coated_keeps_base_emission_and_cutouts() {
+        let emit = Color::new(4.0, 4.0, 4.0);
+        let lamp = Material::Coated {
+            base: Arc::new(Material::DiffuseLight { emit }),
+            ir: 1.5,
+        };
+        let record = HitRecord {
+            front_face: true,
+            ..HitRecord::default()
+        };
+        assert_eq!(lamp.emitted(&record), emit);
+        assert!(!lamp.passes_through(&record));
+
+        let cut_out = Material::Coated {
+            base: Arc::new(Material::Cutout {
+                base: Arc::new(Material::default()),
+                opacity: Texture::Solid(Color::default()),
+            }),
+            ir: 1.5,
+        };
+        assert!(cut_out.passes_through(&record));
+    }
 }
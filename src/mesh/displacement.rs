@@ -0,0 +1,137 @@
+use super::Mesh;
+use crate::euclidean::Point3;
+use crate::texture::Texture;
+use std::collections::HashMap;
+
+impl Mesh {
+    // Triangulates the mesh and splits every triangle into four at its edge
+    // midpoints the given number of times, without smoothing. Neighbouring
+    // triangles share their new vertices so the mesh stays closed.
+    pub fn tessellate(&self, levels: usize) -> Mesh {
+        let triangulated = Mesh {
+            vertices: self.vertices.clone(),
+            faces: self.triangles().iter().map(|t| t.to_vec()).collect(),
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+        };
+        (0..levels).fold(triangulated, |mesh, _| mesh.split_triangles())
+    }
+
+    fn split_triangles(&self) -> Mesh {
+        let mut vertices = self.vertices.clone();
+        let mut uvs = self.uvs.clone();
+        let mut colors = self.colors.clone();
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push((vertices[a] + vertices[b]) / 2.0);
+                if !uvs.is_empty() {
+                    let (ua, va) = uvs[a];
+                    let (ub, vb) = uvs[b];
+                    uvs.push(((ua + ub) / 2.0, (va + vb) / 2.0));
+                }
+                if !colors.is_empty() {
+                    colors.push((colors[a] + colors[b]) / 2.0);
+                }
+                vertices.len() - 1
+            })
+        };
+        let faces = self
+            .faces
+            .iter()
+            .flat_map(|face| {
+                let (a, b, c) = (face[0], face[1], face[2]);
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                vec![
+                    vec![a, ab, ca],
+                    vec![ab, b, bc],
+                    vec![ca, bc, c],
+                    vec![ab, bc, ca],
+                ]
+            })
+            .collect();
+        Mesh {
+            vertices,
+            faces,
+            uvs,
+            colors,
+        }
+    }
+
+    // Moves each vertex out along its normal by scale times the brightness of
+    // the height texture there, looked up with the mesh's texture coordinates
+    // if it has any. Only as much detail as there are vertices shows up, so
+    // the mesh is usually tessellated first. Normals of the displaced surface
+    // come from its new faces like those of any other mesh.
+    pub fn displace(&self, height: &Texture, scale: f64) -> Mesh {
+        let normals = self.vertex_normals();
+        let vertices = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let (u, v) = self.uvs.get(i).copied().unwrap_or((0.0, 0.0));
+                *p + normals[i] * (scale * height.intensity(u, v, p))
+            })
+            .collect::<Vec<Point3>>();
+        Mesh {
+            vertices,
+            faces: self.faces.clone(),
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::{Color, Vec3};
+
+    // unit square in the xz plane facing up
+    fn square() -> Mesh {
+        Mesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 1.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(1.0, 0.0, 0.0),
+            ],
+            vec![vec![0, 1, 2, 3]],
+        )
+        .with_uvs(vec![(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)])
+    }
+
+    #[test]
+    fn test_tessellate_shares_vertices() {
+        let fine = square().tessellate(2);
+        assert_eq!(fine.faces.len(), 2 * 4 * 4);
+        // a 5x5 grid of vertices
+        assert_eq!(fine.vertices.len(), 25);
+        assert_eq!(fine.uvs.len(), 25);
+        for (p, (u, v)) in fine.vertices.iter().zip(&fine.uvs) {
+            assert!((p.x() - u).abs() < 1e-12 && (p.z() - v).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_displace_along_normals() {
+        let flat = square().tessellate(1);
+        let raised = flat.displace(&Texture::Solid(Color::new(0.5, 0.5, 0.5)), 2.0);
+        for (before, after) in flat.vertices.iter().zip(&raised.vertices) {
+            assert!((*after - *before - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_displaced_normals_follow_surface() {
+        let flat = square().tessellate(3);
+        let bumpy = flat.displace(&Texture::Noise { scale: 3.0 }, 0.5);
+        let heights: Vec<f64> = bumpy.vertices.iter().map(|p| p.y()).collect();
+        assert!(heights.iter().any(|h| (h - heights[0]).abs() > 0.01));
+        assert!(bumpy
+            .vertex_normals()
+            .iter()
+            .any(|n| (*n - Vec3::new(0.0, 1.0, 0.0)).len() > 0.1));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::euclidean::{Color, Point3, Vec3};
+use std::io;
+use std::path::Path;
+
+// Polygon mesh as loaded from a file, before it is turned into a hittable.
+// Faces list their vertices counter-clockwise seen from outside and may have
+// any number of sides, though most meshes are made of triangles and quads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    pub faces: Vec<Vec<usize>>,
+    // texture coordinates, one per vertex or none at all
+    pub uvs: Vec<(f64, f64)>,
+    // vertex colors, likewise
+    pub colors: Vec<Color>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Point3>, faces: Vec<Vec<usize>>) -> Self {
+        assert!(
+            faces.iter().flatten().all(|i| *i < vertices.len()),
+            "face refers to a missing vertex"
+        );
+        Self {
+            vertices,
+            faces,
+            uvs: Vec::new(),
+            colors: Vec::new(),
+        }
+    }
+
+    // Loads a PLY or STL file, going by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ply") => Self::load_ply(path),
+            Some("stl") => Self::load_stl(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unsupported mesh file extension",
+            )),
+        }
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(uvs.len(), self.vertices.len());
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        assert_eq!(colors.len(), self.vertices.len());
+        self.colors = colors;
+        self
+    }
+
+    // axis aligned box with a quad for each side
+    pub fn cuboid(min: Point3, max: Point3) -> Self {
+        let vertices = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { min.x() } else { max.x() },
+                    if i & 2 == 0 { min.y() } else { max.y() },
+                    if i & 4 == 0 { min.z() } else { max.z() },
+                )
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ];
+        Self::new(vertices, faces)
+    }
+
+    // splits each face into a fan of triangles around its first vertex
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        self.faces
+            .iter()
+            .flat_map(|face| {
+                (1..face.len().saturating_sub(1)).map(move |i| [face[0], face[i], face[i + 1]])
+            })
+            .collect()
+    }
+
+    // Normal of each face scaled by its area, from Newell's method so that
+    // faces that are not quite planar get a sensible average.
+    pub fn face_normal(&self, face: &[usize]) -> Vec3 {
+        (0..face.len())
+            .map(|i| {
+                let a = self.vertices[face[i]];
+                let b = self.vertices[face[(i + 1) % face.len()]];
+                a.cross(&b)
+            })
+            .fold(Vec3::default(), |acc, n| acc + n)
+            / 2.0
+    }
+
+    // smooth normals at the vertices, weighting each face by its area
+    pub fn vertex_normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::default(); self.vertices.len()];
+        for face in &self.faces {
+            let n = self.face_normal(face);
+            for i in face {
+                normals[*i] += n;
+            }
+        }
+        normals
+            .into_iter()
+            .map(|n| if n.near_zero() { n } else { n.unit_vector() })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangles() {
+        let cube = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        assert_eq!(cube.triangles().len(), 12);
+    }
+
+    #[test]
+    fn test_normals_face_outwards() {
+        let cube = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        for face in &cube.faces {
+            let n = cube.face_normal(face);
+            assert!((n.len() - 4.0).abs() < 1e-12);
+            assert!(n.dot(&cube.vertices[face[0]]) > 0.0);
+        }
+        for (n, p) in cube.vertex_normals().iter().zip(&cube.vertices) {
+            assert!((*n - p.unit_vector()).len() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod displacement;
+#[allow(clippy::module_inception)]
+mod mesh;
+mod ply;
+mod stl;
+mod subdivision;
+
+pub use mesh::Mesh;
@@ -0,0 +1,364 @@
+use super::Mesh;
+use crate::euclidean::{Color, Point3};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+impl Mesh {
+    pub fn load_ply<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse_ply(&fs::read(path)?)
+    }
+
+    // Reads the vertices and faces of a PLY file in any of its three formats.
+    // Vertex colors (red, green, blue) and texture coordinates (u and v or s
+    // and t) are kept when present; other properties and elements are
+    // skipped.
+    pub fn parse_ply(bytes: &[u8]) -> io::Result<Self> {
+        let header_end =
+            find(bytes, b"end_header").ok_or_else(|| invalid("ply header is not terminated"))?;
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .map_err(|_| invalid("ply header is not text"))?;
+        let mut body_start = header_end + b"end_header".len();
+        // the header ends with a single newline, either \n or \r\n
+        if bytes.get(body_start) == Some(&b'\r') {
+            body_start += 1;
+        }
+        body_start += 1;
+
+        let mut lines = header.lines().map(str::trim);
+        if lines.next() != Some("ply") {
+            return Err(invalid("not a ply file"));
+        }
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["format", f, _] => {
+                    format = Some(match *f {
+                        "ascii" => Format::Ascii,
+                        "binary_little_endian" => Format::LittleEndian,
+                        "binary_big_endian" => Format::BigEndian,
+                        _ => return Err(invalid("unknown ply format")),
+                    })
+                }
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| invalid("invalid element count"))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count, item, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property outside of an element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::parse(item)?,
+                        list_count: Some(Scalar::parse(count)?),
+                    }),
+                ["property", kind, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property outside of an element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::parse(kind)?,
+                        list_count: None,
+                    }),
+                _ => (),
+            }
+        }
+        let mut reader = Reader {
+            format: format.ok_or_else(|| invalid("ply format is missing"))?,
+            bytes,
+            pos: body_start,
+        };
+
+        let mut vertices = Vec::new();
+        let mut colors = Vec::new();
+        let mut uvs = Vec::new();
+        let mut faces = Vec::new();
+        for element in &elements {
+            let index = |names: &[&str]| {
+                element
+                    .properties
+                    .iter()
+                    .position(|p| names.contains(&p.name.as_str()))
+            };
+            let position = [index(&["x"]), index(&["y"]), index(&["z"])];
+            let color = [index(&["red"]), index(&["green"]), index(&["blue"])];
+            let uv = [
+                index(&["u", "s", "texture_u", "texture_s"]),
+                index(&["v", "t", "texture_v", "texture_t"]),
+            ];
+            let indices = index(&["vertex_indices", "vertex_index"]);
+            for _ in 0..element.count {
+                let values = element
+                    .properties
+                    .iter()
+                    .map(|p| reader.property(p))
+                    .collect::<io::Result<Vec<Vec<f64>>>>()?;
+                match element.name.as_str() {
+                    "vertex" => {
+                        let [x, y, z] = position.map(|i| i.map_or(0.0, |i| values[i][0]));
+                        vertices.push(Point3::new(x, y, z));
+                        if let [Some(r), Some(g), Some(b)] = color {
+                            // integer colors go up to 255, floating point ones to 1
+                            let scale = |i: usize| {
+                                let value = values[i][0];
+                                if element.properties[i].kind.is_integer() {
+                                    value / 255.0
+                                } else {
+                                    value
+                                }
+                            };
+                            colors.push(Color::new(scale(r), scale(g), scale(b)));
+                        }
+                        if let [Some(u), Some(v)] = uv {
+                            uvs.push((values[u][0], values[v][0]));
+                        }
+                    }
+                    "face" => {
+                        let face = indices.ok_or_else(|| invalid("faces without indices"))?;
+                        // a negative or fractional index can't name a vertex
+                        let face = values[face]
+                            .iter()
+                            .map(|i| {
+                                if *i >= 0.0 && i.fract() == 0.0 {
+                                    Ok(*i as usize)
+                                } else {
+                                    Err(invalid("face refers to a missing vertex"))
+                                }
+                            })
+                            .collect::<io::Result<_>>()?;
+                        faces.push(face);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        if faces.iter().flatten().any(|i: &usize| *i >= vertices.len()) {
+            return Err(invalid("face refers to a missing vertex"));
+        }
+        let mut mesh = Mesh::new(vertices, faces);
+        if !colors.is_empty() {
+            mesh = mesh.with_colors(colors);
+        }
+        if !uvs.is_empty() {
+            mesh = mesh.with_uvs(uvs);
+        }
+        Ok(mesh)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Property {
+    name: String,
+    kind: Scalar,
+    // type of the length prefix for list properties
+    list_count: Option<Scalar>,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(invalid("unknown ply property type")),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        !matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+// Reads property values from the body of a PLY file, which holds the values
+// of each element in turn either as text or in binary.
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn property(&mut self, property: &Property) -> io::Result<Vec<f64>> {
+        match property.list_count {
+            Some(count) => {
+                let count = self.scalar(count)? as usize;
+                (0..count).map(|_| self.scalar(property.kind)).collect()
+            }
+            None => Ok(vec![self.scalar(property.kind)?]),
+        }
+    }
+
+    fn scalar(&mut self, kind: Scalar) -> io::Result<f64> {
+        if let Format::Ascii = self.format {
+            while self
+                .bytes
+                .get(self.pos)
+                .is_some_and(u8::is_ascii_whitespace)
+            {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self
+                .bytes
+                .get(self.pos)
+                .is_some_and(|b| !b.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            return std::str::from_utf8(&self.bytes[start..self.pos])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid("invalid or missing ply value"));
+        }
+
+        let mut raw = self
+            .bytes
+            .get(self.pos..self.pos + kind.size())
+            .ok_or_else(|| invalid("truncated ply body"))?
+            .to_vec();
+        self.pos += kind.size();
+        if let Format::BigEndian = self.format {
+            raw.reverse();
+        }
+        Ok(match kind {
+            Scalar::I8 => raw[0] as i8 as f64,
+            Scalar::U8 => raw[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes([
+                raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7],
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii() {
+        let ply = b"ply\nformat ascii 1.0\ncomment a colored quad\n\
+            element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n\
+            4 0 1 2 3\n";
+        let mesh = Mesh::parse_ply(ply).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2, 3]]);
+        assert_eq!(mesh.colors[1], Color::new(0.0, 1.0, 0.0));
+        assert!(mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        let mut ply = b"ply\r\nformat binary_big_endian 1.0\r\n\
+            element vertex 3\r\nproperty double x\r\nproperty double y\r\nproperty double z\r\n\
+            property float s\r\nproperty float t\r\n\
+            element face 1\r\nproperty list uchar uint vertex_index\r\nend_header\r\n"
+            .to_vec();
+        for (p, uv) in [
+            ([0.0, 0.0, 0.0], [0.0f32, 0.0]),
+            ([1.0, 0.0, 0.0], [1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [0.0, 1.0]),
+        ] {
+            for x in p {
+                ply.extend(f64::to_be_bytes(x));
+            }
+            for u in uv {
+                ply.extend(f32::to_be_bytes(u));
+            }
+        }
+        ply.push(3);
+        for i in [0u32, 1, 2] {
+            ply.extend(i.to_be_bytes());
+        }
+        let mesh = Mesh::parse_ply(&ply).unwrap();
+        assert_eq!(mesh.vertices[1], Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.uvs[2], (0.0, 1.0));
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2]]);
+
+        ply.truncate(ply.len() - 2);
+        assert!(Mesh::parse_ply(&ply).is_err());
+    }
+
+    #[test]
+    fn test_missing_vertex() {
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+            property float y\nproperty float z\nelement face 1\n\
+            property list uchar int vertex_indices\nend_header\n0 0 0\n3 0 1 2\n";
+        assert!(Mesh::parse_ply(ply).is_err());
+
+        // negative and fractional indices used to be cast to vertex 0
+        for (kind, face) in [("int", "3 0 -1 2"), ("float", "3 0 1.5 2")] {
+            let ply = format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+                property float y\nproperty float z\nelement face 1\n\
+                property list uchar {} vertex_indices\nend_header\n\
+                0 0 0\n1 0 0\n0 1 0\n{}\n",
+                kind, face
+            );
+            let error = Mesh::parse_ply(ply.as_bytes()).err().unwrap();
+            assert_eq!(error.to_string(), "face refers to a missing vertex");
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::Mesh;
+use crate::euclidean::Point3;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// bytes of the header and triangle count that start a binary STL file, and
+// of each triangle after them
+const BINARY_HEADER: usize = 84;
+const BINARY_TRIANGLE: usize = 50;
+
+impl Mesh {
+    pub fn load_stl<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse_stl(&fs::read(path)?)
+    }
+
+    // Reads the triangles of an ASCII or binary STL file. STL stores every
+    // triangle with its own corners, and these are kept separate so that the
+    // sharp edges typical of CAD models stay sharp.
+    pub fn parse_stl(bytes: &[u8]) -> io::Result<Self> {
+        // binary files may also start with "solid", so go by their size
+        let binary_count = bytes
+            .get(80..BINARY_HEADER)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+        let corners = match binary_count {
+            Some(count) if bytes.len() == BINARY_HEADER + count * BINARY_TRIANGLE => bytes
+                [BINARY_HEADER..]
+                .chunks(BINARY_TRIANGLE)
+                .flat_map(|triangle| {
+                    // skipping the facet normal
+                    (1..4).map(move |corner| {
+                        let value = |axis: usize| {
+                            let at = 12 * corner + 4 * axis;
+                            let b = &triangle[at..at + 4];
+                            f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+                        };
+                        Point3::new(value(0), value(1), value(2))
+                    })
+                })
+                .collect(),
+            _ => Self::parse_ascii_stl(bytes)?,
+        };
+
+        let faces = (0..corners.len() / 3)
+            .map(|i| vec![3 * i, 3 * i + 1, 3 * i + 2])
+            .collect();
+        Ok(Mesh::new(corners, faces))
+    }
+
+    fn parse_ascii_stl(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+        let text = std::str::from_utf8(bytes).map_err(|_| invalid("stl is not text"))?;
+        if !text.trim_start().starts_with("solid") {
+            return Err(invalid("not an stl file"));
+        }
+        let mut words = text.split_whitespace();
+        let mut corners = Vec::new();
+        while let Some(word) = words.next() {
+            if word != "vertex" {
+                continue;
+            }
+            let mut value = || {
+                words
+                    .next()
+                    .and_then(|w| w.parse::<f64>().ok())
+                    .ok_or_else(|| invalid("invalid stl vertex"))
+            };
+            corners.push(Point3::new(value()?, value()?, value()?));
+        }
+        if corners.len() % 3 != 0 {
+            return Err(invalid("stl facet without three vertices"));
+        }
+        Ok(corners)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii() {
+        let stl = b"solid wedge\n\
+            facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+            facet normal 0 -1 0\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 0 1\n endloop\nendfacet\n\
+            endsolid wedge\n";
+        let mesh = Mesh::parse_stl(stl).unwrap();
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(mesh.vertices[5], Point3::new(0.0, 0.0, 1.0));
+        // corners are not shared, so each face keeps its own normal
+        assert_eq!(mesh.vertex_normals()[0], Point3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.vertex_normals()[3], Point3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        // the header starts like an ascii file
+        let mut stl = b"solid but actually binary".to_vec();
+        stl.resize(80, 0);
+        stl.extend(1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0,
+        ] {
+            stl.extend(value.to_le_bytes());
+        }
+        stl.extend([0, 0]);
+        let mesh = Mesh::parse_stl(&stl).unwrap();
+        assert_eq!(
+            mesh.vertices,
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(0.0, 2.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Mesh::parse_stl(b"solid x\nfacet\nvertex 0 0\n").is_err());
+        assert!(Mesh::parse_stl(b"not an stl").is_err());
+    }
+}
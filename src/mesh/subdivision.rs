@@ -0,0 +1,256 @@
+use super::Mesh;
+use crate::euclidean::Point3;
+use std::collections::HashMap;
+
+impl Mesh {
+    // Refines the mesh with the given number of rounds of Catmull–Clark
+    // subdivision, which turns any polygon mesh into quads converging on a
+    // smooth surface. Boundaries are kept as cubic B-spline curves. Texture
+    // coordinates and colors are interpolated linearly so they don't shrink
+    // away from seams. Faces with fewer than three vertices have no surface
+    // to refine and are dropped.
+    pub fn subdivide(&self, levels: usize) -> Mesh {
+        let mut mesh = self.clone();
+        if levels > 0 {
+            mesh.faces.retain(|face| face.len() >= 3);
+        }
+        (0..levels).fold(mesh, |mesh, _| mesh.catmull_clark())
+    }
+
+    fn catmull_clark(&self) -> Mesh {
+        let average = |points: &mut dyn Iterator<Item = Point3>| {
+            let (sum, n) = points.fold((Point3::default(), 0), |(sum, n), p| (sum + p, n + 1));
+            sum / n as f64
+        };
+        let average_uv = |uvs: &mut dyn Iterator<Item = (f64, f64)>| {
+            let (u, v, n) = uvs.fold((0.0, 0.0, 0), |(u, v, n), uv| (u + uv.0, v + uv.1, n + 1));
+            (u / n as f64, v / n as f64)
+        };
+        let edge_key = |a: usize, b: usize| (a.min(b), a.max(b));
+
+        // faces sharing each edge, with edges numbered in order of appearance
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edge_faces: Vec<Vec<usize>> = Vec::new();
+        let mut edge_ends: Vec<(usize, usize)> = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 0..face.len() {
+                let key = edge_key(face[i], face[(i + 1) % face.len()]);
+                let e = *edges.entry(key).or_insert_with(|| {
+                    edge_faces.push(Vec::new());
+                    edge_ends.push(key);
+                    edge_ends.len() - 1
+                });
+                edge_faces[e].push(f);
+            }
+        }
+
+        let face_points: Vec<Point3> = self
+            .faces
+            .iter()
+            .map(|face| average(&mut face.iter().map(|i| self.vertices[*i])))
+            .collect();
+        let edge_points: Vec<Point3> = edge_ends
+            .iter()
+            .zip(&edge_faces)
+            .map(|((a, b), faces)| {
+                let ends = [self.vertices[*a], self.vertices[*b]];
+                if faces.len() == 2 {
+                    average(
+                        &mut ends
+                            .iter()
+                            .copied()
+                            .chain(faces.iter().map(|f| face_points[*f])),
+                    )
+                } else {
+                    // boundary and non-manifold edges stay straight
+                    average(&mut ends.iter().copied())
+                }
+            })
+            .collect();
+
+        // faces and edges around each vertex
+        let mut vertex_faces = vec![Vec::new(); self.vertices.len()];
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in face {
+                vertex_faces[*i].push(f);
+            }
+        }
+        let mut vertex_edges = vec![Vec::new(); self.vertices.len()];
+        for (e, (a, b)) in edge_ends.iter().enumerate() {
+            vertex_edges[*a].push(e);
+            vertex_edges[*b].push(e);
+        }
+
+        let vertex_points: Vec<Point3> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let boundary: Vec<usize> = vertex_edges[i]
+                    .iter()
+                    .copied()
+                    .filter(|e| edge_faces[*e].len() != 2)
+                    .collect();
+                let midpoint = |e: usize| {
+                    let (a, b) = edge_ends[e];
+                    (self.vertices[a] + self.vertices[b]) / 2.0
+                };
+                match boundary.len() {
+                    0 if !vertex_faces[i].is_empty() => {
+                        let n = vertex_edges[i].len() as f64;
+                        let f = average(&mut vertex_faces[i].iter().map(|f| face_points[*f]));
+                        let r = average(&mut vertex_edges[i].iter().map(|e| midpoint(*e)));
+                        (f + r * 2.0 + *p * (n - 3.0)) / n
+                    }
+                    2 if vertex_faces[i].len() > 1 => {
+                        (midpoint(boundary[0]) + midpoint(boundary[1]) + *p * 2.0) / 4.0
+                    }
+                    // corners of a single face, where boundaries meet, and unused
+                    // vertices stay put
+                    _ => *p,
+                }
+            })
+            .collect();
+
+        // new vertices are the old ones moved, then edge points, then face points
+        let edge_base = self.vertices.len();
+        let face_base = edge_base + edge_points.len();
+        let vertices = vertex_points
+            .into_iter()
+            .chain(edge_points)
+            .chain(face_points)
+            .collect();
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .flat_map(|(f, face)| {
+                let n = face.len();
+                let edge = |i: usize, j: usize| edge_base + edges[&edge_key(face[i], face[j])];
+                (0..n)
+                    .map(|i| {
+                        vec![
+                            face[i],
+                            edge(i, (i + 1) % n),
+                            face_base + f,
+                            edge((i + n - 1) % n, i),
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let uvs = if self.uvs.is_empty() {
+            Vec::new()
+        } else {
+            let edge_uvs = edge_ends
+                .iter()
+                .map(|(a, b)| average_uv(&mut [self.uvs[*a], self.uvs[*b]].iter().copied()));
+            let face_uvs = self
+                .faces
+                .iter()
+                .map(|face| average_uv(&mut face.iter().map(|i| self.uvs[*i])));
+            self.uvs
+                .iter()
+                .copied()
+                .chain(edge_uvs)
+                .chain(face_uvs)
+                .collect()
+        };
+
+        let colors = if self.colors.is_empty() {
+            Vec::new()
+        } else {
+            let edge_colors = edge_ends
+                .iter()
+                .map(|(a, b)| (self.colors[*a] + self.colors[*b]) / 2.0);
+            let face_colors = self
+                .faces
+                .iter()
+                .map(|face| average(&mut face.iter().map(|i| self.colors[*i])));
+            self.colors
+                .iter()
+                .copied()
+                .chain(edge_colors)
+                .chain(face_colors)
+                .collect()
+        };
+
+        Mesh {
+            vertices,
+            faces,
+            uvs,
+            colors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subdivided_cube_rounds_off() {
+        let cube = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let smooth = cube.subdivide(3);
+        assert_eq!(smooth.faces.len(), 6 * 4 * 4 * 4);
+        assert!(smooth.faces.iter().all(|face| face.len() == 4));
+        // the limit surface is close to a sphere well inside the cube
+        for p in &smooth.vertices {
+            let r = p.len();
+            assert!(r > 0.5 && r < 1.0, "radius {}", r);
+        }
+    }
+
+    #[test]
+    fn test_subdivided_cube_vertex_point() {
+        let cube = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let once = cube.subdivide(1);
+        assert_eq!(once.vertices.len(), 8 + 12 + 6);
+        // (F + 2R + (n - 3)P) / n with F = 1/3, R = 2/3 and P = 1 along each axis
+        let corner = once.vertices[7];
+        for i in 0..3 {
+            assert!((corner[i] - 5.0 / 9.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_boundary_stays_in_plane() {
+        // an open grid of two quads in the xz plane
+        let mesh = Mesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 1.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(2.0, 0.0, 1.0),
+            ],
+            vec![vec![0, 3, 4, 1], vec![1, 4, 5, 2]],
+        )
+        .with_uvs(vec![
+            (0.0, 0.0),
+            (0.5, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+            (0.5, 1.0),
+            (1.0, 1.0),
+        ]);
+        let fine = mesh.subdivide(2);
+        assert_eq!(fine.uvs.len(), fine.vertices.len());
+        assert!(fine.vertices.iter().all(|p| p.y() == 0.0));
+        // corners are kept
+        assert_eq!(fine.vertices[0], Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(fine.vertices[5], Point3::new(2.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_degenerate_faces_dropped() {
+        let mut cube = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        cube.faces.push(Vec::new());
+        cube.faces.push(vec![0, 1]);
+        let smooth = cube.subdivide(1);
+        assert_eq!(smooth.faces.len(), 6 * 4);
+        assert!(smooth.vertices.iter().all(|p| p.len().is_finite()));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::euclidean::{Aabb, Ray};
+use crate::hittable::{HitRecord, Hittable, Interval};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    // the left object with the right one cut out of it
+    Difference,
+}
+
+impl CsgOperation {
+    #[inline(always)]
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Self::Union => in_left || in_right,
+            Self::Intersection => in_left && in_right,
+            Self::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Constructive solid geometry combining two closed objects, which may be Csg
+// objects themselves. Surfaces keep the material of the object they come from.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Arc<dyn Hittable + Sync + Send>,
+    right: Arc<dyn Hittable + Sync + Send>,
+    bounds: Option<Aabb>,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: Arc<dyn Hittable + Sync + Send>,
+        right: Arc<dyn Hittable + Sync + Send>,
+    ) -> Self {
+        let (left_box, right_box) = (left.bounding_box(), right.bounding_box());
+        let bounds = match operation {
+            CsgOperation::Union => match (left_box, right_box) {
+                (Some(l), Some(r)) => Some(l.surrounding(&r)),
+                _ => None,
+            },
+            CsgOperation::Intersection => left_box.or(right_box),
+            CsgOperation::Difference => left_box,
+        };
+        Self {
+            operation,
+            left,
+            right,
+            bounds,
+        }
+    }
+
+    pub fn union(
+        left: Arc<dyn Hittable + Sync + Send>,
+        right: Arc<dyn Hittable + Sync + Send>,
+    ) -> Self {
+        Self::new(CsgOperation::Union, left, right)
+    }
+
+    pub fn intersection(
+        left: Arc<dyn Hittable + Sync + Send>,
+        right: Arc<dyn Hittable + Sync + Send>,
+    ) -> Self {
+        Self::new(CsgOperation::Intersection, left, right)
+    }
+
+    pub fn difference(
+        left: Arc<dyn Hittable + Sync + Send>,
+        right: Arc<dyn Hittable + Sync + Send>,
+    ) -> Self {
+        Self::new(CsgOperation::Difference, left, right)
+    }
+
+    fn combine<'a>(&self, left: &[Interval<'a>], right: &[Interval<'a>]) -> Vec<Interval<'a>> {
+        // every boundary of either object as (record, from left, entering)
+        let mut boundaries: Vec<(&HitRecord, bool, bool)> = Vec::new();
+        for (intervals, from_left) in [(left, true), (right, false)] {
+            for interval in intervals {
+                boundaries.push((&interval.enter, from_left, true));
+                boundaries.push((&interval.exit, from_left, false));
+            }
+        }
+        boundaries.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+        // sweep along the ray, tracking whether it is inside of each object
+        let mut combined = Vec::new();
+        let (mut in_left, mut in_right) = (false, false);
+        let mut start: Option<&HitRecord> = None;
+        for (record, from_left, entering) in boundaries {
+            let was_inside = self.operation.inside(in_left, in_right);
+            if from_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+            let inside = self.operation.inside(in_left, in_right);
+            if !was_inside && inside {
+                start = Some(record);
+            } else if was_inside && !inside {
+                // empty intervals come from coincident surfaces
+                if let Some(enter) = start.take().filter(|enter| enter.t < record.t) {
+                    combined.push(Interval {
+                        enter: enter.clone(),
+                        exit: record.clone(),
+                    });
+                }
+            }
+        }
+        combined
+    }
+}
+
+impl Hittable for Csg {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        if let Some(bounds) = self.bounds {
+            if !bounds.hit(ray, t_min, t_max) {
+                return false;
+            }
+        }
+        let intervals = match self.intervals(ray) {
+            Some(intervals) => intervals,
+            None => return false,
+        };
+        for interval in intervals {
+            for (boundary, entering) in [(interval.enter, true), (interval.exit, false)] {
+                if boundary.t >= t_min && boundary.t <= t_max {
+                    // The normal already faces the ray, but a boundary can
+                    // switch from an exit to an entry, e.g. the surface of a
+                    // hole cut out by a difference.
+                    *record = boundary;
+                    record.front_face = entering;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        let left = self.left.intervals(ray)?;
+        let right = self.right.intervals(ray)?;
+        Some(self.combine(&left, &right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::{Point3, Vec3};
+    use crate::material::Material;
+    use crate::object::{Cuboid, Plane, Sphere};
+
+    fn unit_sphere(x: f64) -> Arc<Sphere> {
+        Arc::new(Sphere::new(
+            Point3::new(x, 0.0, 0.0),
+            1.0,
+            Material::default(),
+        ))
+    }
+
+    // covers the positive x half of the unit sphere
+    fn half_space_box() -> Arc<Cuboid> {
+        Arc::new(Cuboid::new(
+            Point3::new(0.0, -2.0, -2.0),
+            Point3::new(2.0, 2.0, 2.0),
+            Material::default(),
+        ))
+    }
+
+    fn along_x(x: f64, direction: f64) -> Ray {
+        Ray::new(Point3::new(x, 0.0, 0.0), Vec3::new(direction, 0.0, 0.0))
+    }
+
+    #[test]
+    fn test_difference() {
+        let csg = Csg::difference(unit_sphere(0.0), half_space_box());
+        let mut record = HitRecord::default();
+        assert!(csg.hit(&along_x(-5.0, 1.0), 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 4.0);
+        assert!(record.front_face);
+
+        // from the cut side, the first surface is the inside wall of the box
+        assert!(csg.hit(&along_x(5.0, -1.0), 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 5.0);
+        assert_eq!(record.normal, Vec3::new(1.0, 0.0, 0.0));
+        assert!(record.front_face);
+
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(!csg.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_intersection() {
+        let csg = Csg::intersection(unit_sphere(0.0), half_space_box());
+        let mut record = HitRecord::default();
+        assert!(csg.hit(&along_x(-5.0, 1.0), 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 5.0);
+        assert_eq!(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!(record.front_face);
+        assert!(csg.hit(&along_x(-5.0, 1.0), 5.5, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 6.0);
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_union() {
+        let csg = Csg::union(unit_sphere(0.0), unit_sphere(1.0));
+        let intervals = csg.intervals(&along_x(-5.0, 1.0)).unwrap();
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].enter.t, 4.0);
+        assert_eq!(intervals[0].exit.t, 7.0);
+
+        // the overlapping surfaces inside the union are skipped
+        let mut record = HitRecord::default();
+        assert!(csg.hit(&along_x(-5.0, 1.0), 4.5, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 7.0);
+    }
+
+    #[test]
+    fn test_nested() {
+        let lens = Arc::new(Csg::intersection(unit_sphere(0.0), unit_sphere(1.0)));
+        let cut = Arc::new(Cuboid::new(
+            Point3::new(0.5, -2.0, -2.0),
+            Point3::new(2.0, 2.0, 2.0),
+            Material::default(),
+        ));
+        let csg = Csg::difference(lens, cut);
+        let intervals = csg.intervals(&along_x(-5.0, 1.0)).unwrap();
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].enter.t, 5.0);
+        assert_eq!(intervals[0].exit.t, 5.5);
+
+        let csg = Csg::union(unit_sphere(-3.0), Arc::new(csg));
+        assert_eq!(csg.intervals(&along_x(-5.0, 1.0)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_coincident_surfaces() {
+        // the box removes the sphere exactly from its leftmost point on
+        let cut = Arc::new(Cuboid::new(
+            Point3::new(-1.0, -2.0, -2.0),
+            Point3::new(2.0, 2.0, 2.0),
+            Material::default(),
+        ));
+        let csg = Csg::difference(unit_sphere(0.0), cut);
+        assert!(csg.intervals(&along_x(-5.0, 1.0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_operand() {
+        let plane = Arc::new(Plane::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Material::default(),
+        ));
+        let csg = Csg::union(unit_sphere(3.0), plane);
+        let mut record = HitRecord::default();
+        assert!(csg.intervals(&along_x(-5.0, 1.0)).is_none());
+        assert!(!csg.hit(&along_x(-5.0, 1.0), 0.0, f64::INFINITY, &mut record));
+    }
+}
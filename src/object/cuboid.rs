@@ -0,0 +1,163 @@
+use crate::euclidean::{Aabb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable, Interval};
+use crate::material::Material;
+use std::borrow::Cow;
+
+// axis aligned box
+pub struct Cuboid {
+    bounds: Aabb,
+    material: Material,
+}
+
+impl Cuboid {
+    pub fn new(a: Point3, b: Point3, material: Material) -> Self {
+        Self {
+            bounds: Aabb::new(a, b),
+            material,
+        }
+    }
+
+    // Where the whole line of the ray enters and leaves the box, along with the
+    // axis of the face crossed at each.
+    fn crossings(&self, ray: &Ray) -> Option<((f64, usize), (f64, usize))> {
+        let mut enter = (f64::NEG_INFINITY, 0);
+        let mut exit = (f64::INFINITY, 0);
+        for axis in 0..3 {
+            let (min, max) = (self.bounds.min[axis], self.bounds.max[axis]);
+            if ray.dir[axis] == 0.0 {
+                // parallel to this pair of faces
+                if ray.origin[axis] < min || ray.origin[axis] > max {
+                    return None;
+                }
+                continue;
+            }
+            let inv_d = 1.0 / ray.dir[axis];
+            let mut near = (min - ray.origin[axis]) * inv_d;
+            let mut far = (max - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            if near > enter.0 {
+                enter = (near, axis);
+            }
+            if far < exit.0 {
+                exit = (far, axis);
+            }
+        }
+        if enter.0 < exit.0 {
+            Some((enter, exit))
+        } else {
+            None
+        }
+    }
+
+    fn set_record<'a>(&'a self, ray: &Ray, t: f64, axis: usize, record: &mut HitRecord<'a>) {
+        let p = ray.at(t);
+        let mut outward_normal = Vec3::default();
+        outward_normal[axis] = if p[axis] < self.bounds.centroid()[axis] {
+            -1.0
+        } else {
+            1.0
+        };
+        // texture coordinates span each face along the two other axes
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let size = self.bounds.max - self.bounds.min;
+        record.t = t;
+        record.p = p;
+        record.set_face_normal(ray, &outward_normal);
+        record.u = (p[a] - self.bounds.min[a]) / size[a];
+        record.v = (p[b] - self.bounds.min[b]) / size[b];
+        record.tangent = Vec3::default();
+        record.tangent[a] = 1.0;
+        record.dp_du = size[a];
+        record.dp_dv = size[b];
+        record.material = Cow::Borrowed(&self.material);
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let ((t_enter, enter_axis), (t_exit, exit_axis)) = match self.crossings(ray) {
+            Some(crossings) => crossings,
+            None => return false,
+        };
+        if t_enter >= t_min && t_enter <= t_max {
+            self.set_record(ray, t_enter, enter_axis, record);
+        } else if t_exit >= t_min && t_exit <= t_max {
+            self.set_record(ray, t_exit, exit_axis, record);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        Some(match self.crossings(ray) {
+            Some(((t_enter, enter_axis), (t_exit, exit_axis))) => {
+                let mut enter = HitRecord::default();
+                let mut exit = HitRecord::default();
+                self.set_record(ray, t_enter, enter_axis, &mut enter);
+                self.set_record(ray, t_exit, exit_axis, &mut exit);
+                vec![Interval { enter, exit }]
+            }
+            None => vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cuboid() -> Cuboid {
+        Cuboid::new(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 2.0, 1.0),
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let cuboid = cuboid();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(cuboid.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 4.0);
+        assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(record.front_face);
+
+        // from inside the box
+        assert!(cuboid.hit(&ray, 5.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 6.0);
+        assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_miss() {
+        let cuboid = cuboid();
+        let ray = Ray::new(Point3::new(0.0, 2.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(!cuboid.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!cuboid.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let cuboid = cuboid();
+        let mut record = HitRecord::default();
+        // touching an edge
+        let ray = Ray::new(Point3::new(-3.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 1.0));
+        assert!(!cuboid.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        // parallel to a face, just inside of it
+        let ray = Ray::new(Point3::new(-5.0, 1.999, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cuboid.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+    }
+}
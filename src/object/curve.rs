@@ -0,0 +1,297 @@
+use crate::euclidean::{Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::f64::consts::PI;
+
+// Cubic Bézier curve swept with a width varying linearly along it, for hair,
+// fur and grass. It is intersected as a flat ribbon facing the ray but shaded
+// like a cylinder, which is indistinguishable at the widths it is meant for.
+// The intersection follows pbrt's recursive subdivision of the curve in the
+// coordinate space of the ray.
+pub struct Curve {
+    control_points: [Point3; 4],
+    width: [f64; 2],
+    bounds: Aabb,
+    material: Material,
+}
+
+// limit on how many times the curve is halved before testing it as a line
+const MAX_DEPTH: i32 = 10;
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+fn blossom(cp: &[Vec3; 4], t: f64) -> Vec3 {
+    let a = [
+        cp[0] * (1.0 - t) + cp[1] * t,
+        cp[1] * (1.0 - t) + cp[2] * t,
+        cp[2] * (1.0 - t) + cp[3] * t,
+    ];
+    let b = [a[0] * (1.0 - t) + a[1] * t, a[1] * (1.0 - t) + a[2] * t];
+    b[0] * (1.0 - t) + b[1] * t
+}
+
+fn derivative(cp: &[Vec3; 4], t: f64) -> Vec3 {
+    let a = [cp[1] - cp[0], cp[2] - cp[1], cp[3] - cp[2]];
+    let b = [a[0] * (1.0 - t) + a[1] * t, a[1] * (1.0 - t) + a[2] * t];
+    (b[0] * (1.0 - t) + b[1] * t) * 3.0
+}
+
+// splits the curve in half with de Casteljau's algorithm
+fn subdivide(cp: &[Vec3; 4]) -> ([Vec3; 4], [Vec3; 4]) {
+    let mid = |a: Vec3, b: Vec3| (a + b) / 2.0;
+    let a = [mid(cp[0], cp[1]), mid(cp[1], cp[2]), mid(cp[2], cp[3])];
+    let b = [mid(a[0], a[1]), mid(a[1], a[2])];
+    let c = mid(b[0], b[1]);
+    ([cp[0], a[0], b[0], c], [c, b[1], a[2], cp[3]])
+}
+
+impl Curve {
+    pub fn new(control_points: [Point3; 4], width0: f64, width1: f64, material: Material) -> Self {
+        let bounds = control_points[1..]
+            .iter()
+            .fold(Aabb::new(control_points[0], control_points[0]), |b, p| {
+                b.surrounding(&Aabb::new(*p, *p))
+            })
+            .pad(width0.max(width1) / 2.0);
+        Self {
+            control_points,
+            width: [width0, width1],
+            bounds,
+            material,
+        }
+    }
+
+    pub fn point_at(&self, u: f64) -> Point3 {
+        blossom(&self.control_points, u)
+    }
+
+    // Finds the closest hit of the ray, which runs along +z from the origin in
+    // the space of these control points, with the part of the curve between
+    // u0 and u1. Returns the distance along the ray with the curve parameter
+    // and the offset across the curve in [0, 1].
+    fn intersect(
+        &self,
+        cp: &[Vec3; 4],
+        z_range: (f64, f64),
+        (u0, u1): (f64, f64),
+        depth: i32,
+    ) -> Option<(f64, f64, f64)> {
+        let max_width =
+            lerp(u0, self.width[0], self.width[1]).max(lerp(u1, self.width[0], self.width[1]));
+        let bounds = cp[1..]
+            .iter()
+            .fold(Aabb::new(cp[0], cp[0]), |b, p| {
+                b.surrounding(&Aabb::new(*p, *p))
+            })
+            .pad(max_width / 2.0);
+        if bounds.min.x() > 0.0
+            || bounds.max.x() < 0.0
+            || bounds.min.y() > 0.0
+            || bounds.max.y() < 0.0
+            || bounds.max.z() < z_range.0
+            || bounds.min.z() > z_range.1
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let (first, second) = subdivide(cp);
+            let u_mid = (u0 + u1) / 2.0;
+            let hit = self.intersect(&first, z_range, (u0, u_mid), depth - 1);
+            let z_range = match hit {
+                Some((z, _, _)) => (z_range.0, z),
+                None => z_range,
+            };
+            return self
+                .intersect(&second, z_range, (u_mid, u1), depth - 1)
+                .or(hit);
+        }
+
+        // close enough to a line; reject hits past either end of it
+        let edge_start = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        let edge_end = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge_start < 0.0 || edge_end < 0.0 {
+            return None;
+        }
+        let segment = Vec3::new(cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y(), 0.0);
+        let denom = segment.len_squared();
+        if denom == 0.0 {
+            return None;
+        }
+        let w = (Vec3::new(-cp[0].x(), -cp[0].y(), 0.0).dot(&segment) / denom).clamp(0.0, 1.0);
+        let u = lerp(w, u0, u1);
+        let hit_width = lerp(u, self.width[0], self.width[1]);
+        let p = blossom(cp, w);
+        let distance_squared = p.x() * p.x() + p.y() * p.y();
+        if distance_squared > hit_width * hit_width / 4.0 || p.z() < z_range.0 || p.z() > z_range.1
+        {
+            return None;
+        }
+        // which side of the curve's center line the ray passes
+        let dp = derivative(cp, w);
+        let side = if dp.x() * -p.y() + p.x() * dp.y() > 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        let v = 0.5 + side * distance_squared.sqrt() / hit_width;
+        Some((p.z(), u, v))
+    }
+
+    // levels of subdivision needed for the curve to be approximated by lines
+    // to within a fraction of its width
+    fn depth(&self, cp: &[Vec3; 4]) -> i32 {
+        let l0 = (0..2)
+            .map(|i| {
+                let d = cp[i] - cp[i + 1] * 2.0 + cp[i + 2];
+                d.x().abs().max(d.y().abs()).max(d.z().abs())
+            })
+            .fold(0.0, f64::max);
+        let epsilon = self.width[0].max(self.width[1]) * 0.05;
+        if l0 <= 0.0 || epsilon <= 0.0 {
+            return 0;
+        }
+        let r0 = (2.0f64.sqrt() * 6.0 * l0 / (8.0 * epsilon)).log2() / 2.0;
+        (r0.round() as i32).clamp(0, MAX_DEPTH)
+    }
+}
+
+impl Hittable for Curve {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        if !self.bounds.hit(ray, t_min, t_max) {
+            return false;
+        }
+        let dir_len = ray.dir.len();
+        let ray_space = Onb::from_w(&ray.dir);
+        let cp = self
+            .control_points
+            .map(|p| ray_space.to_local(&(p - ray.origin)));
+        let z_range = (t_min * dir_len, t_max.min(f64::MAX) * dir_len);
+        let (z, u, v) = match self.intersect(&cp, z_range, (0.0, 1.0), self.depth(&cp)) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let derivative = derivative(&self.control_points, u);
+        let tangent = if derivative.near_zero() {
+            self.control_points[3] - self.control_points[0]
+        } else {
+            derivative
+        }
+        .unit_vector();
+        // normal of the ribbon facing the ray, turned around the tangent to
+        // give the normal of a cylinder at this offset across it
+        let facing = -ray.dir - tangent * (-ray.dir).dot(&tangent);
+        let facing = if facing.near_zero() {
+            Onb::from_w(&tangent).u
+        } else {
+            facing.unit_vector()
+        };
+        let across = tangent.cross(&facing);
+        let angle = (v - 0.5) * PI;
+        let normal = facing * angle.cos() + across * angle.sin();
+
+        record.t = z / dir_len;
+        record.p = ray.at(record.t);
+        record.set_face_normal(ray, &normal);
+        record.u = u;
+        record.v = v;
+        record.tangent = tangent;
+        record.dp_du = derivative.len();
+        record.dp_dv = lerp(u, self.width[0], self.width[1]);
+        record.material = Cow::Borrowed(&self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // straight along x from -1 to 1, tapering from 0.2 to 0.1 wide
+    fn strand() -> Curve {
+        Curve::new(
+            [
+                Point3::new(-1.0, 0.0, 0.0),
+                Point3::new(-1.0 / 3.0, 0.0, 0.0),
+                Point3::new(1.0 / 3.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+            ],
+            0.2,
+            0.1,
+            Material::default(),
+        )
+    }
+
+    // arching over the origin in the xy plane
+    fn arch() -> Curve {
+        Curve::new(
+            [
+                Point3::new(-1.0, 0.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+            ],
+            0.1,
+            0.1,
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let strand = strand();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(strand.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 5.0).abs() < 1e-6);
+        assert!((record.u - 0.5).abs() < 1e-6);
+        assert!((record.v - 0.5).abs() < 1e-6);
+        assert!((record.normal - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-6);
+        assert!((record.tangent - Vec3::new(1.0, 0.0, 0.0)).len() < 1e-6);
+    }
+
+    #[test]
+    fn test_hit_curved() {
+        let arch = arch();
+        let ray = Ray::new(Point3::new(0.0, 0.75, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(arch.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.u - 0.5).abs() < 1e-3);
+        let ray = Ray::new(Point3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!arch.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_miss() {
+        let strand = strand();
+        let mut record = HitRecord::default();
+        // beyond the narrower end's half width
+        let ray = Ray::new(Point3::new(0.9, 0.06, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!strand.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        // past the end of the curve
+        let ray = Ray::new(Point3::new(1.05, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!strand.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!strand.hit(&ray, 0.0, 4.0, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let strand = strand();
+        let mut record = HitRecord::default();
+        // 0.195 across at this point
+        let inside = Ray::new(Point3::new(-0.9, 0.095, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(strand.hit(&inside, 0.0, f64::INFINITY, &mut record));
+        assert!(record.v > 0.95 || record.v < 0.05);
+        let outside = Ray::new(Point3::new(-0.9, 0.1, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!strand.hit(&outside, 0.0, f64::INFINITY, &mut record));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::euclidean::roots::solve_quadratic;
+use crate::euclidean::{Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable, Interval};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::f64::consts::PI;
+
+// capped cylinder standing on its base, extending along its axis
+pub struct Cylinder {
+    base: Point3,
+    frame: Onb,
+    radius: f64,
+    height: f64,
+    material: Material,
+}
+
+#[derive(Clone, Copy)]
+enum Part {
+    Side,
+    Bottom,
+    Top,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, height: f64, material: Material) -> Self {
+        Self {
+            base,
+            frame: Onb::from_w(&axis),
+            radius,
+            height,
+            material,
+        }
+    }
+
+    // every crossing of the ray with the cylinder's surface, in local
+    // coordinates where the axis is +z
+    fn crossings(&self, o: &Vec3, d: &Vec3) -> [Option<(f64, Part)>; 4] {
+        let mut crossings = [None; 4];
+        let side = solve_quadratic([
+            o.x() * o.x() + o.y() * o.y() - self.radius * self.radius,
+            2.0 * (o.x() * d.x() + o.y() * d.y()),
+            d.x() * d.x() + d.y() * d.y(),
+        ]);
+        for (slot, t) in crossings.iter_mut().zip(side) {
+            let z = o.z() + t * d.z();
+            if (0.0..=self.height).contains(&z) {
+                *slot = Some((t, Part::Side));
+            }
+        }
+        if d.z() != 0.0 {
+            let caps = [(0.0, Part::Bottom), (self.height, Part::Top)];
+            for (slot, (z, part)) in crossings[2..].iter_mut().zip(caps) {
+                let t = (z - o.z()) / d.z();
+                let x = o.x() + t * d.x();
+                let y = o.y() + t * d.y();
+                if x * x + y * y <= self.radius * self.radius {
+                    *slot = Some((t, part));
+                }
+            }
+        }
+        crossings
+    }
+
+    fn set_record<'a>(
+        &'a self,
+        ray: &Ray,
+        o: &Vec3,
+        d: &Vec3,
+        t: f64,
+        part: Part,
+        record: &mut HitRecord<'a>,
+    ) {
+        let p = *o + *d * t;
+        let r = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let around = (p.y().atan2(p.x()) / (2.0 * PI)).rem_euclid(1.0);
+        let (outward_normal, u, v) = match part {
+            Part::Side => (
+                Vec3::new(p.x(), p.y(), 0.0) / self.radius,
+                around,
+                p.z() / self.height,
+            ),
+            Part::Bottom => (Vec3::new(0.0, 0.0, -1.0), around, r / self.radius),
+            Part::Top => (Vec3::new(0.0, 0.0, 1.0), around, r / self.radius),
+        };
+        record.dp_du = 2.0 * PI * r;
+        record.dp_dv = match part {
+            Part::Side => self.height,
+            Part::Bottom | Part::Top => self.radius,
+        };
+        record.t = t;
+        record.p = ray.at(t);
+        record.set_face_normal(ray, &self.frame.local(&outward_normal));
+        record.u = u;
+        record.v = v;
+        record.tangent = if r > 0.0 {
+            self.frame.local(&Vec3::new(-p.y(), p.x(), 0.0)) / r
+        } else {
+            self.frame.u
+        };
+        record.material = Cow::Borrowed(&self.material);
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let o = self.frame.to_local(&(ray.origin - self.base));
+        let d = self.frame.to_local(&ray.dir);
+        let closest = self
+            .crossings(&o, &d)
+            .iter()
+            .flatten()
+            .filter(|(t, _)| *t >= t_min && *t <= t_max)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .copied();
+        let (t, part) = match closest {
+            Some(closest) => closest,
+            None => return false,
+        };
+        self.set_record(ray, &o, &d, t, part, record);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let axis = self.frame.w;
+        let bottom = Aabb::around_disk(&self.base, &axis, self.radius);
+        let top = Aabb::around_disk(&(self.base + axis * self.height), &axis, self.radius);
+        Some(bottom.surrounding(&top))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        let o = self.frame.to_local(&(ray.origin - self.base));
+        let d = self.frame.to_local(&ray.dir);
+        // convex, so the ray is inside between its first and last crossing
+        let crossings = self.crossings(&o, &d);
+        let first = crossings
+            .iter()
+            .flatten()
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let last = crossings
+            .iter()
+            .flatten()
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        match (first, last) {
+            (Some(&(t0, part0)), Some(&(t1, part1))) if t0 < t1 => {
+                let mut enter = HitRecord::default();
+                let mut exit = HitRecord::default();
+                self.set_record(ray, &o, &d, t0, part0, &mut enter);
+                self.set_record(ray, &o, &d, t1, part1, &mut exit);
+                Some(vec![Interval { enter, exit }])
+            }
+            _ => Some(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // unit radius, standing on the origin up to y = 2
+    fn cylinder() -> Cylinder {
+        Cylinder::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            1.0,
+            2.0,
+            Material::default(),
+        )
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_hit_side() {
+        let cylinder = cylinder();
+        let ray = Ray::new(Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(cylinder.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 4.0).abs() < 1e-9);
+        assert_near(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!((record.v - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hit_caps() {
+        let cylinder = cylinder();
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(cylinder.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 3.0).abs() < 1e-9);
+        assert_near(record.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        // from inside, the bottom cap is hit from its back
+        let ray = Ray::new(Point3::new(0.5, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 1.0).abs() < 1e-9);
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_miss() {
+        let cylinder = cylinder();
+        let mut record = HitRecord::default();
+        let above = Ray::new(Point3::new(-5.0, 2.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!cylinder.hit(&above, 0.0, f64::INFINITY, &mut record));
+        let beside = Ray::new(Point3::new(1.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(!cylinder.hit(&beside, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let cylinder = cylinder();
+        let mut record = HitRecord::default();
+        let outside = Ray::new(Point3::new(-5.0, 1.0, 1.0 + 1e-6), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!cylinder.hit(&outside, 0.0, f64::INFINITY, &mut record));
+        let inside = Ray::new(Point3::new(-5.0, 1.0, 1.0 - 1e-6), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cylinder.hit(&inside, 0.0, f64::INFINITY, &mut record));
+        // parallel to the axis along the side wall
+        let along = Ray::new(Point3::new(1.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.hit(&along, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = cylinder().bounding_box().unwrap();
+        assert_near(b.min, Point3::new(-1.0, 0.0, -1.0));
+        assert_near(b.max, Point3::new(1.0, 2.0, 1.0));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::euclidean::{Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::f64::consts::PI;
+
+pub struct Disk {
+    center: Point3,
+    frame: Onb,
+    radius: f64,
+    material: Material,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Material) -> Self {
+        Self {
+            center,
+            frame: Onb::from_w(&normal),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let normal = self.frame.w;
+        let denom = ray.dir.dot(&normal);
+        if denom.abs() < 1e-12 {
+            return false;
+        }
+        let t = (self.center - ray.origin).dot(&normal) / denom;
+        if t < t_min || t > t_max {
+            return false;
+        }
+        let p = ray.at(t);
+        let local = self.frame.to_local(&(p - self.center));
+        let r = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        if r > self.radius {
+            return false;
+        }
+        record.t = t;
+        record.p = p;
+        record.set_face_normal(ray, &normal);
+        // polar coordinates, u around the center and v outwards
+        record.u = (local.y().atan2(local.x()) / (2.0 * PI)).rem_euclid(1.0);
+        record.v = r / self.radius;
+        record.tangent = if r > 0.0 {
+            self.frame.local(&Vec3::new(-local.y(), local.x(), 0.0)) / r
+        } else {
+            self.frame.u
+        };
+        record.dp_du = 2.0 * PI * r;
+        record.dp_dv = self.radius;
+        record.material = Cow::Borrowed(&self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::around_disk(&self.center, &self.frame.w, self.radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk() -> Disk {
+        Disk::new(
+            Point3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let disk = disk();
+        let ray = Ray::new(Point3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(disk.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(record.v, 0.5);
+    }
+
+    #[test]
+    fn test_miss() {
+        let disk = disk();
+        let ray = Ray::new(Point3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(!disk.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!disk.hit(&ray, 0.0, 1.0, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let disk = disk();
+        let ray = Ray::new(Point3::new(-5.0, 0.0, -2.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(!disk.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = disk().bounding_box().unwrap();
+        assert_eq!(b.min, Point3::new(-1.0, -1.0, -2.0));
+        assert_eq!(b.max, Point3::new(1.0, 1.0, -2.0));
+    }
+}
@@ -0,0 +1,452 @@
+use super::triangle::intersect_triangle;
+use crate::euclidean::{clamp, Aabb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::texture::{perlin, Image};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+
+// Terrain given by heights sampled on a regular grid over the xz plane. Each
+// grid cell is split into two triangles, shaded with smoothly interpolated
+// normals.
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "SavedHeightfield")]
+pub struct Heightfield {
+    // nx * nz heights above the origin, in rows of increasing z
+    heights: Vec<f64>,
+    // derived from the heights, so left out of scene files
+    #[serde(skip_serializing)]
+    normals: Vec<Vec3>,
+    nx: usize,
+    nz: usize,
+    // corner of the grid with the smallest x and z
+    origin: Point3,
+    cell_x: f64,
+    cell_z: f64,
+    #[serde(skip_serializing)]
+    bounds: Aabb,
+    material: Material,
+}
+
+// the fields of a heightfield that are saved
+#[derive(Deserialize)]
+struct SavedHeightfield {
+    heights: Vec<f64>,
+    nx: usize,
+    nz: usize,
+    origin: Point3,
+    cell_x: f64,
+    cell_z: f64,
+    material: Material,
+}
+
+impl TryFrom<SavedHeightfield> for Heightfield {
+    type Error = String;
+
+    fn try_from(saved: SavedHeightfield) -> Result<Self, Self::Error> {
+        if saved.nx < 2
+            || saved.nz < 2
+            || saved.nx.checked_mul(saved.nz) != Some(saved.heights.len())
+        {
+            return Err("heightfield heights don't match its size".to_string());
+        }
+        let valid_cell = |size: f64| size > 0.0 && size.is_finite();
+        if !valid_cell(saved.cell_x) || !valid_cell(saved.cell_z) {
+            return Err("heightfield cells need a positive size".to_string());
+        }
+        Ok(Self::with_cells(
+            saved.heights,
+            saved.nx,
+            saved.nz,
+            saved.origin,
+            saved.cell_x,
+            saved.cell_z,
+            saved.material,
+        ))
+    }
+}
+
+impl Heightfield {
+    // Builds a field spanning size_x by size_z from its corner at origin.
+    pub fn new(
+        heights: Vec<f64>,
+        nx: usize,
+        nz: usize,
+        origin: Point3,
+        size_x: f64,
+        size_z: f64,
+        material: Material,
+    ) -> Self {
+        assert!(nx >= 2 && nz >= 2, "heightfields need at least 2x2 samples");
+        let cell_x = size_x / (nx - 1) as f64;
+        let cell_z = size_z / (nz - 1) as f64;
+        Self::with_cells(heights, nx, nz, origin, cell_x, cell_z, material)
+    }
+
+    // the spacing of the samples rather than the size of the field, so loaded
+    // fields come out exactly as they were saved
+    fn with_cells(
+        heights: Vec<f64>,
+        nx: usize,
+        nz: usize,
+        origin: Point3,
+        cell_x: f64,
+        cell_z: f64,
+        material: Material,
+    ) -> Self {
+        assert!(nx >= 2 && nz >= 2, "heightfields need at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz);
+
+        let (lowest, highest) = heights
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), h| {
+                (lo.min(*h), hi.max(*h))
+            });
+        let bounds = Aabb::new(
+            origin + Vec3::new(0.0, lowest, 0.0),
+            origin + Vec3::new(cell_x * (nx - 1) as f64, highest, cell_z * (nz - 1) as f64),
+        );
+
+        // vertex normals from central differences of the heights
+        let height = |i: usize, k: usize| heights[k * nx + i];
+        let normals = (0..nz)
+            .flat_map(|k| (0..nx).map(move |i| (i, k)))
+            .map(|(i, k)| {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (k0, k1) = (k.saturating_sub(1), (k + 1).min(nz - 1));
+                let dh_dx = (height(i1, k) - height(i0, k)) / ((i1 - i0) as f64 * cell_x);
+                let dh_dz = (height(i, k1) - height(i, k0)) / ((k1 - k0) as f64 * cell_z);
+                Vec3::new(-dh_dx, 1.0, -dh_dz).unit_vector()
+            })
+            .collect();
+
+        Self {
+            heights,
+            normals,
+            nx,
+            nz,
+            origin,
+            cell_x,
+            cell_z,
+            bounds,
+            material,
+        }
+    }
+
+    // Heights from the brightness of a grayscale image, scaled to size.y. The
+    // top row of the image is at the smallest z. The image needs at least 2x2
+    // pixels.
+    pub fn from_image(
+        image: &Image,
+        origin: Point3,
+        size: Vec3,
+        material: Material,
+    ) -> io::Result<Self> {
+        let (nx, nz) = (image.width(), image.height());
+        if nx < 2 || nz < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "heightfield images need at least 2x2 pixels",
+            ));
+        }
+        let heights = (0..nz)
+            .flat_map(|k| (0..nx).map(move |i| (i, k)))
+            .map(|(i, k)| {
+                let c = image.pixel(i, k);
+                size.y() * (c.x() + c.y() + c.z()) / 3.0
+            })
+            .collect();
+        Ok(Self::new(
+            heights,
+            nx,
+            nz,
+            origin,
+            size.x(),
+            size.z(),
+            material,
+        ))
+    }
+
+    // Rolling terrain from fractal noise with features about 1 / frequency
+    // across, scaled to size.y.
+    pub fn from_noise(
+        nx: usize,
+        nz: usize,
+        origin: Point3,
+        size: Vec3,
+        frequency: f64,
+        octaves: usize,
+        material: Material,
+    ) -> Self {
+        let heights = (0..nz)
+            .flat_map(|k| (0..nx).map(move |i| (i, k)))
+            .map(|(i, k)| {
+                let x = size.x() * i as f64 / (nx - 1) as f64;
+                let z = size.z() * k as f64 / (nz - 1) as f64;
+                let n = perlin().fbm(&(Point3::new(x, 0.0, z) * frequency), octaves);
+                size.y() * clamp(0.5 * (1.0 + n), 0.0, 1.0)
+            })
+            .collect();
+        Self::new(heights, nx, nz, origin, size.x(), size.z(), material)
+    }
+
+    #[inline(always)]
+    fn height(&self, i: usize, k: usize) -> f64 {
+        self.heights[k * self.nx + i]
+    }
+
+    #[inline(always)]
+    fn vertex(&self, i: usize, k: usize) -> Point3 {
+        self.origin
+            + Vec3::new(
+                i as f64 * self.cell_x,
+                self.height(i, k),
+                k as f64 * self.cell_z,
+            )
+    }
+
+    // Height of the surface above the point (x, z) in world coordinates, if
+    // the point is over the field.
+    pub fn height_at(&self, x: f64, z: f64) -> Option<f64> {
+        let gx = (x - self.origin.x()) / self.cell_x;
+        let gz = (z - self.origin.z()) / self.cell_z;
+        if gx < 0.0 || gz < 0.0 || gx > (self.nx - 1) as f64 || gz > (self.nz - 1) as f64 {
+            return None;
+        }
+        let i = (gx.floor() as usize).min(self.nx - 2);
+        let k = (gz.floor() as usize).min(self.nz - 2);
+        let (fx, fz) = (gx - i as f64, gz - k as f64);
+        // planar within each of the cell's two triangles
+        let h = if fx + fz <= 1.0 {
+            let h00 = self.height(i, k);
+            h00 + fx * (self.height(i + 1, k) - h00) + fz * (self.height(i, k + 1) - h00)
+        } else {
+            let h11 = self.height(i + 1, k + 1);
+            h11 + (1.0 - fx) * (self.height(i, k + 1) - h11)
+                + (1.0 - fz) * (self.height(i + 1, k) - h11)
+        };
+        Some(self.origin.y() + h)
+    }
+
+    // closest hit on the two triangles of a cell, with its shading normal
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        i: usize,
+        k: usize,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, Vec3)> {
+        let triangles = [
+            [(i, k), (i, k + 1), (i + 1, k)],
+            [(i + 1, k), (i, k + 1), (i + 1, k + 1)],
+        ];
+        triangles
+            .iter()
+            .filter_map(|corners| {
+                let [a, b, c] = corners.map(|(i, k)| self.vertex(i, k));
+                let (t, b1, b2) = intersect_triangle(ray, &a, &b, &c)
+                    .filter(|(t, _, _)| *t >= t_min && *t <= t_max)?;
+                let [n0, n1, n2] = corners.map(|(i, k)| self.normals[k * self.nx + i]);
+                Some((t, (n0 * (1.0 - b1 - b2) + n1 * b1 + n2 * b2).unit_vector()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let (t_start, t_end) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return false,
+        };
+
+        // walk the cells under the ray in order with a 2d DDA, so the first
+        // hit found is the closest
+        let start = ray.at(t_start);
+        let gx = (start.x() - self.origin.x()) / self.cell_x;
+        let gz = (start.z() - self.origin.z()) / self.cell_z;
+        let mut i = (gx.floor().max(0.0) as usize).min(self.nx - 2);
+        let mut k = (gz.floor().max(0.0) as usize).min(self.nz - 2);
+
+        let axis_setup = |d: f64, o: f64, corner: f64, cell: f64, index: usize| {
+            if d > 0.0 {
+                (1, (corner + (index + 1) as f64 * cell - o) / d, cell / d)
+            } else if d < 0.0 {
+                (-1, (corner + index as f64 * cell - o) / d, -cell / d)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) =
+            axis_setup(ray.dir.x(), ray.origin.x(), self.origin.x(), self.cell_x, i);
+        let (step_z, mut next_z, delta_z) =
+            axis_setup(ray.dir.z(), ray.origin.z(), self.origin.z(), self.cell_z, k);
+
+        loop {
+            if let Some((t, normal)) = self.hit_cell(ray, i, k, t_min, t_max) {
+                record.t = t;
+                record.p = ray.at(t);
+                record.set_face_normal(ray, &normal);
+                let extent = self.bounds.max - self.bounds.min;
+                record.u = (record.p.x() - self.origin.x()) / extent.x();
+                record.v = (record.p.z() - self.origin.z()) / extent.z();
+                // the axes projected onto the slope, which stretches the
+                // distance covered per unit of u and v
+                let along = |axis: Vec3| axis - normal * normal.dot(&axis);
+                let (along_x, along_z) = (
+                    along(Vec3::new(1.0, 0.0, 0.0)),
+                    along(Vec3::new(0.0, 0.0, 1.0)),
+                );
+                record.tangent = along_x.unit_vector();
+                record.dp_du = extent.x() / along_x.len();
+                record.dp_dv = extent.z() / along_z.len();
+                record.material = Cow::Borrowed(&self.material);
+                return true;
+            }
+
+            let t_next = next_x.min(next_z);
+            if t_next > t_end {
+                return false;
+            }
+            if next_x < next_z {
+                if (step_x < 0 && i == 0) || (step_x > 0 && i + 2 >= self.nx) {
+                    return false;
+                }
+                i = (i as i64 + step_x) as usize;
+                next_x += delta_x;
+            } else {
+                if (step_z < 0 && k == 0) || (step_z > 0 && k + 2 >= self.nz) {
+                    return false;
+                }
+                k = (k as i64 + step_z) as usize;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        Some(json!({ "heightfield": self }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::Color;
+
+    // a ramp rising along x from 0 to 2 over a 4x4 square
+    fn ramp() -> Heightfield {
+        let (nx, nz) = (5, 5);
+        let heights = (0..nz)
+            .flat_map(|_| (0..nx).map(|i| i as f64 * 0.5))
+            .collect();
+        Heightfield::new(
+            heights,
+            nx,
+            nz,
+            Point3::new(-2.0, 0.0, -2.0),
+            4.0,
+            4.0,
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn test_height_at() {
+        let field = ramp();
+        assert_eq!(field.height_at(-2.0, 0.0), Some(0.0));
+        assert_eq!(field.height_at(0.25, 0.7), Some(1.125));
+        assert_eq!(field.height_at(2.0, 2.0), Some(2.0));
+        assert_eq!(field.height_at(2.5, 0.0), None);
+    }
+
+    #[test]
+    fn test_hit_from_above() {
+        let field = ramp();
+        let ray = Ray::new(Point3::new(0.5, 10.0, 0.3), Vec3::new(0.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.p.y() - 1.25).abs() < 1e-9);
+        let s = 0.2f64.sqrt();
+        assert!((record.normal - Vec3::new(-s, 2.0 * s, 0.0)).len() < 1e-9);
+        assert!(record.front_face);
+    }
+
+    #[test]
+    fn test_hit_across_cells() {
+        // travels over several cells before meeting the slope
+        let field = ramp();
+        let ray = Ray::new(Point3::new(5.0, 1.0, 0.1), Vec3::new(-1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.p.x() - 0.0).abs() < 1e-9);
+        // from underneath the slope
+        assert!(!record.front_face);
+        let ray = Ray::new(Point3::new(-5.0, 1.0, 0.1), Vec3::new(1.0, 0.0, 0.0));
+        assert!(field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.p.x() - 0.0).abs() < 1e-9);
+        assert!(record.front_face);
+    }
+
+    #[test]
+    fn test_invalid_saved() {
+        let saved = serde_json::to_value(ramp()).unwrap();
+        assert!(serde_json::from_value::<Heightfield>(saved.clone()).is_ok());
+        let with = |field: &str, value: Value| {
+            let mut saved = saved.clone();
+            saved[field] = value;
+            serde_json::from_value::<Heightfield>(saved).is_err()
+        };
+        // sizes whose product overflows
+        assert!(with("nx", json!(usize::MAX)));
+        assert!(with("cell_x", json!(0.0)));
+        assert!(with("cell_z", json!(-1.0)));
+    }
+
+    #[test]
+    fn test_from_image() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let image = Image::new(2, 2, vec![Color::default(), white, white, white]);
+        let size = Vec3::new(1.0, 3.0, 1.0);
+        let field =
+            Heightfield::from_image(&image, Point3::default(), size, Material::default()).unwrap();
+        // the image's top left pixel is at the origin
+        assert_eq!(field.height_at(0.0, 0.0), Some(0.0));
+        assert_eq!(field.height_at(1.0, 1.0), Some(3.0));
+
+        let line = Image::new(2, 1, vec![white, white]);
+        assert!(
+            Heightfield::from_image(&line, Point3::default(), size, Material::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_miss() {
+        let field = ramp();
+        let mut record = HitRecord::default();
+        let ray = Ray::new(Point3::new(-5.0, 2.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(3.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(!field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        // skims the ramp just above its surface, parallel to the slope
+        let field = ramp();
+        let ray = Ray::new(
+            Point3::new(-3.0, -0.5 + 1e-6, 0.5),
+            Vec3::new(2.0, 1.0, 0.0),
+        );
+        let mut record = HitRecord::default();
+        assert!(!field.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+}
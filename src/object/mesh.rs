@@ -0,0 +1,293 @@
+use super::triangle::intersect_triangle;
+use crate::euclidean::{Aabb, Color, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::texture::Texture;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+// Triangulated mesh shaded with smoothly interpolated vertex normals. The
+// triangles are kept in a bounding volume hierarchy of their own so large
+// meshes can sit in a HittableList next to a handful of spheres. Vertex
+// colors, if the mesh has them, tint the material.
+pub struct TriangleMesh {
+    vertices: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    colors: Vec<Color>,
+    triangles: Vec<[usize; 3]>,
+    nodes: Vec<Node>,
+    material: Arc<Material>,
+}
+
+// triangles per leaf before a node is split
+const LEAF_SIZE: usize = 4;
+
+// Node of the hierarchy, stored depth first so the first child of an interior
+// node directly follows it.
+struct Node {
+    bounds: Aabb,
+    // leaves cover triangles[start..start + count], interior nodes have a
+    // count of zero and their second child at start
+    start: usize,
+    count: usize,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: &Mesh, material: Material) -> Self {
+        let mut triangles = mesh.triangles();
+        let bounds: Vec<Aabb> = triangles
+            .iter()
+            .map(|t| Self::triangle_bounds(&mesh.vertices, t))
+            .collect();
+        let mut order: Vec<usize> = (0..triangles.len()).collect();
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            Self::build(&bounds, &mut order, 0, &mut nodes);
+        }
+        triangles = order.iter().map(|i| triangles[*i]).collect();
+
+        Self {
+            vertices: mesh.vertices.clone(),
+            normals: mesh.vertex_normals(),
+            uvs: mesh.uvs.clone(),
+            colors: mesh.colors.clone(),
+            triangles,
+            nodes,
+            material: Arc::new(material),
+        }
+    }
+
+    fn triangle_bounds(vertices: &[Point3], triangle: &[usize; 3]) -> Aabb {
+        let [a, b, c] = triangle.map(|i| vertices[i]);
+        // padded so triangles lying in an axis plane still have some volume
+        Aabb::new(a, b).surrounding(&Aabb::new(c, c)).pad(1e-9)
+    }
+
+    // Builds the subtree over order[..], whose entries index into bounds and
+    // start at offset in the final triangle order, splitting at the median
+    // centroid along the widest axis.
+    fn build(bounds: &[Aabb], order: &mut [usize], offset: usize, nodes: &mut Vec<Node>) {
+        let node_bounds = order[1..]
+            .iter()
+            .fold(bounds[order[0]], |b, i| b.surrounding(&bounds[*i]));
+        let index = nodes.len();
+        nodes.push(Node {
+            bounds: node_bounds,
+            start: offset,
+            count: order.len(),
+        });
+        if order.len() <= LEAF_SIZE {
+            return;
+        }
+
+        let extent = node_bounds.max - node_bounds.min;
+        let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        };
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |a, b| {
+            bounds[*a].centroid()[axis].total_cmp(&bounds[*b].centroid()[axis])
+        });
+        let (left, right) = order.split_at_mut(mid);
+        Self::build(bounds, left, offset, nodes);
+        let second = nodes.len();
+        Self::build(bounds, right, offset + mid, nodes);
+        nodes[index].start = second;
+        nodes[index].count = 0;
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let mut closest: Option<(usize, f64, f64, f64)> = None;
+        let mut closest_so_far = t_max;
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+            for i in node.start..node.start + node.count {
+                let [a, b, c] = self.triangles[i].map(|v| self.vertices[v]);
+                if let Some((t, b1, b2)) = intersect_triangle(ray, &a, &b, &c) {
+                    if t >= t_min && t < closest_so_far {
+                        closest_so_far = t;
+                        closest = Some((i, t, b1, b2));
+                    }
+                }
+            }
+        }
+
+        let (i, t, b1, b2) = match closest {
+            Some(hit) => hit,
+            None => return false,
+        };
+        let triangle = self.triangles[i];
+        let [a, b, c] = triangle.map(|v| self.vertices[v]);
+        let weights = [1.0 - b1 - b2, b1, b2];
+        let interpolate = |values: [Vec3; 3]| {
+            values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+        };
+
+        let geometric = (b - a).cross(&(c - a)).unit_vector();
+        let smooth = interpolate(triangle.map(|v| self.normals[v]));
+        let shading = if smooth.near_zero() {
+            geometric
+        } else {
+            smooth.unit_vector()
+        };
+        // which side is hit is decided by the actual surface, not the normals
+        // interpolated over it
+        record.t = t;
+        record.p = ray.at(t);
+        record.front_face = ray.dir.dot(&geometric) < 0.0;
+        record.normal = if record.front_face { shading } else { -shading };
+
+        let (u, v, tangent, bitangent) = if self.uvs.is_empty() {
+            (b1, b2, b - a, c - a)
+        } else {
+            let [uv0, uv1, uv2] = triangle.map(|v| self.uvs[v]);
+            let uv = interpolate([uv0, uv1, uv2].map(|(u, v)| Vec3::new(u, v, 0.0)));
+            // direction of increasing u across the triangle
+            let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+            let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+            let det = du1 * dv2 - du2 * dv1;
+            let (tangent, bitangent) = if det.abs() < 1e-12 {
+                (b - a, c - a)
+            } else {
+                (
+                    ((b - a) * dv2 - (c - a) * dv1) / det,
+                    ((c - a) * du1 - (b - a) * du2) / det,
+                )
+            };
+            (uv.x(), uv.y(), tangent, bitangent)
+        };
+        record.u = u;
+        record.v = v;
+        record.dp_du = tangent.len();
+        record.dp_dv = bitangent.len();
+        let tangent = tangent - shading * shading.dot(&tangent);
+        record.tangent = if tangent.near_zero() {
+            tangent
+        } else {
+            tangent.unit_vector()
+        };
+        record.material = if self.colors.is_empty() {
+            Cow::Borrowed(&*self.material)
+        } else {
+            let color = interpolate(triangle.map(|v| self.colors[v]));
+            Cow::Owned(Material::Textured {
+                base: self.material.clone(),
+                albedo: Texture::Solid(color),
+            })
+        };
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> TriangleMesh {
+        let mesh = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        TriangleMesh::new(&mesh, Material::default())
+    }
+
+    #[test]
+    fn test_hit() {
+        let cube = cube();
+        let ray = Ray::new(Point3::new(0.2, 0.3, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(cube.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 4.0).abs() < 1e-9);
+        assert!(record.front_face);
+        // the smooth normal leans towards the corners
+        assert!(record.normal.z() > 0.5);
+        assert!(record.normal.x() > 0.0 && record.normal.y() > 0.0);
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let cube = cube();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(cube.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 1.0).abs() < 1e-9);
+        assert!(!record.front_face);
+        assert!(record.normal.x() < 0.0);
+    }
+
+    #[test]
+    fn test_miss() {
+        let cube = cube();
+        let mut record = HitRecord::default();
+        let ray = Ray::new(Point3::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!cube.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!cube.hit(&ray, 0.0, 3.0, &mut record));
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let mesh = Mesh::new(
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 2]],
+        )
+        .with_colors(vec![red, blue, blue]);
+        let triangle = TriangleMesh::new(&mesh, Material::default());
+        let ray = Ray::new(Point3::new(-1.0, -1.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut record = HitRecord::default();
+        assert!(triangle.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        match *record.material {
+            Material::Textured {
+                albedo: Texture::Solid(color),
+                ..
+            } => assert!((color - red).len() < 1e-9),
+            _ => panic!("vertex colors should tint the material"),
+        }
+    }
+
+    #[test]
+    fn test_closest_of_many() {
+        // a finely subdivided cube has a deep hierarchy to search
+        let mesh = Mesh::cuboid(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let smooth = TriangleMesh::new(&mesh.subdivide(4), Material::default());
+        let mut record = HitRecord::default();
+        for dir in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ] {
+            let ray = Ray::new(dir * 5.0, -dir);
+            assert!(smooth.hit(&ray, 0.0, f64::INFINITY, &mut record));
+            let expected = 5.0 * dir.len() - record.p.len();
+            assert!((record.t * dir.len() - expected).abs() < 1e-9);
+            assert!(record.normal.dot(&dir.unit_vector()) > 0.99);
+        }
+    }
+}
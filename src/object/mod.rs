@@ -1,3 +1,28 @@
+mod cone;
+mod csg;
+mod cuboid;
+mod curve;
+mod cylinder;
+mod disk;
+mod heightfield;
+mod mesh;
+mod plane;
+mod sdf;
 mod sphere;
+mod torus;
+mod transformed;
+mod triangle;
 
+pub use cone::Cone;
+pub use csg::{Csg, CsgOperation};
+pub use cuboid::Cuboid;
+pub use curve::Curve;
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use heightfield::Heightfield;
+pub use mesh::TriangleMesh;
+pub use plane::Plane;
+pub use sdf::{DistanceField, Sdf};
 pub use sphere::Sphere;
+pub use torus::Torus;
+pub use transformed::Transformed;
@@ -0,0 +1,102 @@
+use crate::euclidean::{Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use std::borrow::Cow;
+
+// infinite plane, textured with world space coordinates along the plane
+pub struct Plane {
+    point: Point3,
+    frame: Onb,
+    material: Material,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Material) -> Self {
+        Self {
+            point,
+            frame: Onb::from_w(&normal),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let normal = self.frame.w;
+        let denom = ray.dir.dot(&normal);
+        if denom.abs() < 1e-12 {
+            // parallel to the plane
+            return false;
+        }
+        let t = (self.point - ray.origin).dot(&normal) / denom;
+        if t < t_min || t > t_max {
+            return false;
+        }
+        record.t = t;
+        record.p = ray.at(t);
+        record.set_face_normal(ray, &normal);
+        let local = self.frame.to_local(&(record.p - self.point));
+        record.u = local.x();
+        record.v = local.y();
+        record.tangent = self.frame.u;
+        record.dp_du = 1.0;
+        record.dp_dv = 1.0;
+        record.material = Cow::Borrowed(&self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground() -> Plane {
+        Plane::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let ground = ground();
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(ground.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.p, Point3::new(2.0, 1.0, 0.0));
+        assert_eq!(record.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(record.front_face);
+    }
+
+    #[test]
+    fn test_hit_from_below() {
+        let ground = ground();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(ground.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!(!record.front_face);
+        assert_eq!(record.normal, Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_miss() {
+        let ground = ground();
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(!ground.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let ground = ground();
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(!ground.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+}
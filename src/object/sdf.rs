@@ -0,0 +1,271 @@
+use crate::euclidean::{clamp, Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+const MAX_STEPS: usize = 512;
+// distance from the surface at which a ray counts as hitting it
+const HIT_EPSILON: f64 = 1e-5;
+// step used to estimate normals from the gradient of the field
+const NORMAL_EPSILON: f64 = 1e-5;
+
+// Signed distance function, negative inside of the shape. Combinators build
+// new fields out of existing ones.
+#[derive(Clone)]
+pub struct DistanceField(Arc<dyn Fn(&Point3) -> f64 + Send + Sync>);
+
+impl DistanceField {
+    pub fn new<F: Fn(&Point3) -> f64 + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    #[inline(always)]
+    pub fn distance(&self, p: &Point3) -> f64 {
+        (self.0)(p)
+    }
+
+    pub fn gradient(&self, p: &Point3) -> Vec3 {
+        let e = NORMAL_EPSILON;
+        let d = |x: f64, y: f64, z: f64| self.distance(&(*p + Vec3::new(x, y, z)));
+        Vec3::new(
+            d(e, 0.0, 0.0) - d(-e, 0.0, 0.0),
+            d(0.0, e, 0.0) - d(0.0, -e, 0.0),
+            d(0.0, 0.0, e) - d(0.0, 0.0, -e),
+        ) / (2.0 * e)
+    }
+
+    pub fn sphere(center: Point3, radius: f64) -> Self {
+        Self::new(move |p| (*p - center).len() - radius)
+    }
+
+    // axis aligned box
+    pub fn cuboid(center: Point3, half_extents: Vec3) -> Self {
+        Self::new(move |p| {
+            let d = *p - center;
+            let q = Vec3::new(
+                d.x().abs() - half_extents.x(),
+                d.y().abs() - half_extents.y(),
+                d.z().abs() - half_extents.z(),
+            );
+            let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).len();
+            outside + q.x().max(q.y()).max(q.z()).min(0.0)
+        })
+    }
+
+    // torus around the y axis
+    pub fn torus(center: Point3, major_radius: f64, minor_radius: f64) -> Self {
+        Self::new(move |p| {
+            let d = *p - center;
+            let ring = (d.x() * d.x() + d.z() * d.z()).sqrt() - major_radius;
+            (ring * ring + d.y() * d.y()).sqrt() - minor_radius
+        })
+    }
+
+    pub fn translate(self, offset: Vec3) -> Self {
+        Self::new(move |p| self.distance(&(*p - offset)))
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self::new(move |p| self.distance(p).min(other.distance(p)))
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self::new(move |p| self.distance(p).max(other.distance(p)))
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        Self::new(move |p| self.distance(p).max(-other.distance(p)))
+    }
+
+    // union blending the two shapes together over a distance of about k
+    pub fn smooth_union(self, other: Self, k: f64) -> Self {
+        Self::new(move |p| {
+            let a = self.distance(p);
+            let b = other.distance(p);
+            let h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+            b + (a - b) * h - k * h * (1.0 - h)
+        })
+    }
+
+    // Repeats the shape infinitely with the given period along each axis, or
+    // not at all along axes with a period of zero. The shape should fit in
+    // the cell around the origin.
+    pub fn repeat(self, period: Vec3) -> Self {
+        Self::new(move |p| {
+            let mut q = *p;
+            for axis in 0..3 {
+                if period[axis] > 0.0 {
+                    q[axis] -= period[axis] * (p[axis] / period[axis]).round();
+                }
+            }
+            self.distance(&q)
+        })
+    }
+
+    // Twists the shape around the y axis by rate radians per unit of height.
+    // This stretches space, so the result overestimates distances further
+    // from the axis; trace it with a step scale below one.
+    pub fn twist(self, rate: f64) -> Self {
+        Self::new(move |p| {
+            let (sin, cos) = (rate * p.y()).sin_cos();
+            let q = Point3::new(cos * p.x() - sin * p.z(), p.y(), sin * p.x() + cos * p.z());
+            self.distance(&q)
+        })
+    }
+}
+
+// Shape given by a distance field, intersected by sphere tracing within its
+// bounds.
+pub struct Sdf {
+    field: DistanceField,
+    bounds: Aabb,
+    material: Material,
+    step_scale: f64,
+}
+
+impl Sdf {
+    pub fn new(field: DistanceField, bounds: Aabb, material: Material) -> Self {
+        Self {
+            field,
+            bounds,
+            material,
+            step_scale: 1.0,
+        }
+    }
+
+    // fraction of the distance to the surface to step at a time, for fields
+    // that overestimate it
+    pub fn with_step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+}
+
+impl Hittable for Sdf {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let (t_start, t_end) = match self.bounds.hit_interval(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return false,
+        };
+        let dir_len = ray.dir.len();
+
+        // rays starting inside the shape trace the distance to its surface
+        // from within, and rays leaving the surface they start on have to get
+        // away from it before they can hit anything
+        let mut t = t_start;
+        let d0 = self.field.distance(&ray.at(t));
+        let mut sign = d0.signum();
+        if d0.abs() < HIT_EPSILON {
+            let leaving = self.field.gradient(&ray.at(t)).dot(&ray.dir) > 0.0;
+            sign = if leaving { 1.0 } else { -1.0 };
+            t += 2.0 * HIT_EPSILON / dir_len;
+        }
+
+        for _ in 0..MAX_STEPS {
+            if t > t_end {
+                return false;
+            }
+            let d = sign * self.field.distance(&ray.at(t));
+            if d < HIT_EPSILON {
+                record.t = t;
+                record.p = ray.at(t);
+                let outward_normal = self.field.gradient(&record.p).unit_vector();
+                record.set_face_normal(ray, &outward_normal);
+                record.u = 0.0;
+                record.v = 0.0;
+                record.tangent = Onb::from_w(&outward_normal).u;
+                record.dp_du = 0.0;
+                record.dp_dv = 0.0;
+                record.material = Cow::Borrowed(&self.material);
+                return true;
+            }
+            t += d * self.step_scale / dir_len;
+        }
+        false
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_bounds() -> Aabb {
+        Aabb::new(Point3::new(-2.0, -2.0, -2.0), Point3::new(2.0, 2.0, 2.0))
+    }
+
+    #[test]
+    fn test_primitives() {
+        let origin = Point3::default();
+        let sphere = DistanceField::sphere(origin, 1.0);
+        assert_eq!(sphere.distance(&Point3::new(3.0, 0.0, 0.0)), 2.0);
+        let cuboid = DistanceField::cuboid(origin, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(cuboid.distance(&Point3::new(0.0, 0.0, 5.0)), 2.0);
+        assert_eq!(cuboid.distance(&origin), -1.0);
+        let torus = DistanceField::torus(origin, 2.0, 0.5);
+        assert_eq!(torus.distance(&Point3::new(2.0, 0.0, 0.0)), -0.5);
+        assert_eq!(torus.distance(&origin), 1.5);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let a = DistanceField::sphere(Point3::new(-1.0, 0.0, 0.0), 1.0);
+        let b = DistanceField::sphere(Point3::new(1.0, 0.0, 0.0), 1.0);
+        let p = Point3::new(0.0, 1.0, 0.0);
+        let union = a.clone().union(b.clone()).distance(&p);
+        // blending fills in the crease between the spheres
+        assert!(a.clone().smooth_union(b.clone(), 0.5).distance(&p) < union);
+        assert!(a.clone().intersection(b.clone()).distance(&p) >= union);
+
+        let repeated =
+            DistanceField::sphere(Point3::default(), 0.5).repeat(Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(repeated.distance(&Point3::new(10.0, 0.0, 0.0)), -0.5);
+        assert_eq!(repeated.distance(&Point3::new(0.0, 10.0, 0.0)), 9.5);
+
+        let twisted = DistanceField::cuboid(Point3::default(), Vec3::new(1.0, 2.0, 0.1))
+            .twist(std::f64::consts::FRAC_PI_2);
+        // a quarter turn at y = 1 swaps the box's x and z extents
+        assert!(twisted.distance(&Point3::new(0.0, 1.0, 0.9)) < 0.0);
+        assert!(twisted.distance(&Point3::new(0.9, 1.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn test_hit() {
+        let sdf = Sdf::new(
+            DistanceField::sphere(Point3::default(), 1.0),
+            unit_bounds(),
+            Material::default(),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        let mut record = HitRecord::default();
+        assert!(sdf.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 2.0).abs() < 1e-4);
+        assert!((record.normal - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-4);
+        assert!(record.front_face);
+
+        // continuing from the surface into the sphere finds its far side
+        let inner = Ray::new(record.p, ray.dir);
+        assert!(sdf.hit(&inner, 0.0, f64::INFINITY, &mut record));
+        assert!((record.p.z() + 1.0).abs() < 1e-4);
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_miss() {
+        let sdf = Sdf::new(
+            DistanceField::sphere(Point3::default(), 1.0),
+            unit_bounds(),
+            Material::default(),
+        );
+        let mut record = HitRecord::default();
+        let ray = Ray::new(Point3::new(0.0, 1.1, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!sdf.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        // leaving the surface it starts on
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!sdf.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+}
@@ -1,8 +1,12 @@
-use crate::euclidean::{Point3, Ray};
-use crate::hittable::{HitRecord, Hittable};
+use crate::euclidean::{Aabb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable, Interval};
 use crate::material::Material;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::f64::consts::PI;
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct Sphere {
     center: Point3,
     radius: f64,
@@ -19,8 +23,44 @@ impl Sphere {
     }
 }
 
+impl Sphere {
+    // u is the angle around the y axis starting from -x, v the angle from -y,
+    // both mapped to [0, 1]
+    fn uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn set_record<'a>(&'a self, ray: &Ray, t: f64, record: &mut HitRecord<'a>) {
+        record.t = t;
+        record.p = ray.at(record.t);
+        let outward_normal = (record.p - self.center) / self.radius;
+        record.set_face_normal(ray, &outward_normal);
+        let (u, v) = Self::uv(&outward_normal);
+        record.u = u;
+        record.v = v;
+        record.tangent = Self::tangent(&outward_normal);
+        let sin_theta = (1.0 - outward_normal.y().powf(2.0)).max(0.0).sqrt();
+        record.dp_du = 2.0 * PI * self.radius * sin_theta;
+        record.dp_dv = PI * self.radius;
+        record.material = Cow::Borrowed(&self.material);
+    }
+
+    // direction of dp/du for a point on the unit sphere
+    fn tangent(p: &Point3) -> Vec3 {
+        let t = Vec3::new(p.z(), 0.0, -p.x());
+        if t.near_zero() {
+            // at the poles
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            t.unit_vector()
+        }
+    }
+}
+
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
         let oc = ray.origin - self.center;
         let a = ray.dir.len_squared();
         let half_b = oc.dot(&ray.dir);
@@ -38,12 +78,35 @@ impl Hittable for Sphere {
                     return false;
                 }
             }
-            record.t = root;
-            record.p = ray.at(record.t);
-            let outward_normal = (record.p - self.center) / self.radius;
-            record.set_face_normal(&ray, &outward_normal);
-            record.material = self.material;
+            self.set_record(ray, root, record);
             true
         }
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        Some(json!({ "sphere": self }))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        let oc = ray.origin - self.center;
+        let a = ray.dir.len_squared();
+        let half_b = oc.dot(&ray.dir);
+        let c = oc.len_squared() - self.radius.powf(2.0);
+
+        let discriminant = half_b.powf(2.0) - a * c;
+        if discriminant <= 0.0 {
+            return Some(vec![]);
+        }
+        let discriminant_sqrt = discriminant.sqrt();
+        let mut enter = HitRecord::default();
+        let mut exit = HitRecord::default();
+        self.set_record(ray, (-half_b - discriminant_sqrt) / a, &mut enter);
+        self.set_record(ray, (-half_b + discriminant_sqrt) / a, &mut exit);
+        Some(vec![Interval { enter, exit }])
+    }
 }
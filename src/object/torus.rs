@@ -0,0 +1,276 @@
+use crate::euclidean::roots::{polish_root, solve_quartic};
+use crate::euclidean::{Aabb, Onb, Point3, Ray, Vec3};
+use crate::hittable::{HitRecord, Hittable, Interval};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::f64::consts::PI;
+
+// Cosine between the ray and the normal below which a crossing is taken to
+// only touch the surface, neither entering nor leaving the tube.
+const TANGENT_EPSILON: f64 = 1e-6;
+// distance from the surface, relative to the torus' size, within which a
+// root is taken to be a crossing
+const SURFACE_EPSILON: f64 = 1e-6;
+
+// Torus around an axis through its center. The major radius is the distance
+// from the center to the middle of the tube, the minor radius that of the tube.
+pub struct Torus {
+    center: Point3,
+    frame: Onb,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Material,
+    ) -> Self {
+        Self {
+            center,
+            frame: Onb::from_w(&axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    // Every crossing of the ray with the torus, in local coordinates where the
+    // axis is +z. Returned as parameters along the ray, unsorted.
+    fn crossings(&self, o: &Vec3, d: &Vec3) -> Vec<f64> {
+        let ray_dir = *d;
+        // solving with a unit direction keeps the quartic well conditioned
+        let len = d.len();
+        let d = *d / len;
+        let r2 = self.major_radius * self.major_radius;
+        let e = o.len_squared() + r2 - self.minor_radius * self.minor_radius;
+        let f = o.dot(&d);
+        // (|o + td|² + R² - r²)² = 4R²((o + td)ₓ² + (o + td)ᵧ²)
+        let c = [
+            e * e - 4.0 * r2 * (o.x() * o.x() + o.y() * o.y()),
+            4.0 * f * e - 8.0 * r2 * (o.x() * d.x() + o.y() * d.y()),
+            2.0 * e + 4.0 * f * f - 4.0 * r2 * (d.x() * d.x() + d.y() * d.y()),
+            4.0 * f,
+            1.0,
+        ];
+        // the double root of a grazing ray is badly conditioned, and solving
+        // for it can give stray roots nowhere near the surface
+        let tolerance = SURFACE_EPSILON * (self.major_radius + self.minor_radius);
+        solve_quartic(c)
+            .into_iter()
+            .map(|t| polish_root(&c, t, 2) / len)
+            .filter(|t| {
+                let p = *o + ray_dir * *t;
+                ((p - self.ring(&p)).len() - self.minor_radius).abs() < tolerance
+            })
+            .collect()
+    }
+
+    // nearest point to p on the circle running through the middle of the tube
+    fn ring(&self, p: &Vec3) -> Vec3 {
+        let rho = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        if rho > 0.0 {
+            Vec3::new(p.x(), p.y(), 0.0) * (self.major_radius / rho)
+        } else {
+            Vec3::new(self.major_radius, 0.0, 0.0)
+        }
+    }
+
+    fn set_record<'a>(&'a self, ray: &Ray, o: &Vec3, d: &Vec3, t: f64, record: &mut HitRecord<'a>) {
+        let p = *o + *d * t;
+        let rho = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let outward_normal = (p - self.ring(&p)).unit_vector();
+        record.t = t;
+        record.p = ray.at(t);
+        record.set_face_normal(ray, &self.frame.local(&outward_normal));
+        // u around the axis, v around the tube
+        record.u = (p.y().atan2(p.x()) / (2.0 * PI)).rem_euclid(1.0);
+        record.v = (p.z().atan2(rho - self.major_radius) / (2.0 * PI)).rem_euclid(1.0);
+        record.tangent = if rho > 0.0 {
+            self.frame.local(&Vec3::new(-p.y(), p.x(), 0.0)) / rho
+        } else {
+            self.frame.u
+        };
+        record.dp_du = 2.0 * PI * rho;
+        record.dp_dv = 2.0 * PI * self.minor_radius;
+        record.material = Cow::Borrowed(&self.material);
+    }
+}
+
+impl Hittable for Torus {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        let o = self.frame.to_local(&(ray.origin - self.center));
+        let d = self.frame.to_local(&ray.dir);
+        let t = match self
+            .crossings(&o, &d)
+            .into_iter()
+            .filter(|t| *t >= t_min && *t <= t_max)
+            .min_by(|a, b| a.total_cmp(b))
+        {
+            Some(t) => t,
+            None => return false,
+        };
+
+        self.set_record(ray, &o, &d, t, record);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(
+            Aabb::around_disk(&self.center, &self.frame.w, self.major_radius)
+                .pad(self.minor_radius),
+        )
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        let o = self.frame.to_local(&(ray.origin - self.center));
+        let d = self.frame.to_local(&ray.dir);
+        let mut crossings = self.crossings(&o, &d);
+        crossings.sort_by(|a, b| a.total_cmp(b));
+
+        // A grazing ray touches the surface at a double root that may come out
+        // as one crossing or two, so crossings can't be paired up in order.
+        // Each is classified by the side of the surface the ray comes from
+        // instead, skipping those that only touch it.
+        let unit_dir = d.unit_vector();
+        let mut spans = Vec::new();
+        let mut enter = None;
+        for t in crossings {
+            let p = o + d * t;
+            let facing = (p - self.ring(&p)).unit_vector().dot(&unit_dir);
+            match enter {
+                None if facing < -TANGENT_EPSILON => enter = Some(t),
+                Some(t_enter) if facing > TANGENT_EPSILON => {
+                    spans.push((t_enter, t));
+                    enter = None;
+                }
+                _ => {}
+            }
+        }
+        Some(
+            spans
+                .into_iter()
+                .map(|(t_enter, t_exit)| {
+                    let mut enter = HitRecord::default();
+                    let mut exit = HitRecord::default();
+                    self.set_record(ray, &o, &d, t_enter, &mut enter);
+                    self.set_record(ray, &o, &d, t_exit, &mut exit);
+                    Interval { enter, exit }
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lying in the xz plane, tube from 1 to 3 units from the center
+    fn torus() -> Torus {
+        Torus::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            1.0,
+            Material::default(),
+        )
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_hit_outer_wall() {
+        let torus = torus();
+        let ray = Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(torus.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 7.0).abs() < 1e-6);
+        assert_near(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+        // past the first wall, the next crossing is the tube's inner wall
+        assert!(torus.hit(&ray, 7.5, f64::INFINITY, &mut record));
+        assert!((record.t - 9.0).abs() < 1e-6);
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_hit_from_above() {
+        let torus = torus();
+        let ray = Ray::new(Point3::new(2.0, 10.0, 0.0), Vec3::new(0.0, -2.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(torus.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 4.5).abs() < 1e-6);
+        assert_near(record.normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_miss_through_hole() {
+        let torus = torus();
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(!torus.hit(&ray, 0.0, f64::INFINITY, &mut record));
+        let ray = Ray::new(Point3::new(-10.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!torus.hit(&ray, 0.0, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_grazing() {
+        let torus = torus();
+        let mut record = HitRecord::default();
+        let above = Ray::new(
+            Point3::new(-10.0, 1.0 + 1e-4, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert!(!torus.hit(&above, 0.0, f64::INFINITY, &mut record));
+        let below = Ray::new(
+            Point3::new(-10.0, 1.0 - 1e-4, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert!(torus.hit(&below, 0.0, f64::INFINITY, &mut record));
+        assert!((record.t - 8.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_intervals() {
+        let torus = torus();
+        let ray = Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let intervals = torus.intervals(&ray).unwrap();
+        assert_eq!(intervals.len(), 2);
+        assert!((intervals[0].enter.t - 7.0).abs() < 1e-6);
+        assert!((intervals[0].exit.t - 9.0).abs() < 1e-6);
+        assert!((intervals[1].enter.t - 11.0).abs() < 1e-6);
+        assert!((intervals[1].exit.t - 13.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_intervals_grazing_inner_rim() {
+        // Touches the inner wall at x = 0 while inside the tube to either
+        // side. Depending on where the ray starts, the double root there comes
+        // out as one crossing, two, or a pair of stray ones.
+        let torus = torus();
+        let half = 8.0_f64.sqrt();
+        for x in [-10.0, -7.3, -3.1] {
+            let ray = Ray::new(Point3::new(x, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+            let intervals = torus.intervals(&ray).unwrap();
+            assert!(!intervals.is_empty());
+            assert!((intervals[0].enter.t - (-x - half)).abs() < 1e-6);
+            assert!((intervals[intervals.len() - 1].exit.t - (-x + half)).abs() < 1e-6);
+            for pair in intervals.windows(2) {
+                assert!(pair[1].enter.t - pair[0].exit.t < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let b = torus().bounding_box().unwrap();
+        assert_near(b.min, Point3::new(-3.0, -1.0, -3.0));
+        assert_near(b.max, Point3::new(3.0, 1.0, 3.0));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::euclidean::{Aabb, Point3, Ray, Transform};
+use crate::hittable::{HitRecord, Hittable, Interval};
+use crate::material::Material;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+// An object moved, turned or scaled by a transform, optionally with its
+// material replaced, e.g. an object placed as at one frame of an animation.
+pub struct Transformed {
+    object: Arc<dyn Hittable + Sync + Send>,
+    transform: Transform,
+    inverse: Transform,
+    material: Option<Material>,
+    bounds: Option<Aabb>,
+}
+
+impl Transformed {
+    // None if the transform collapses the object flat
+    pub fn new(object: Arc<dyn Hittable + Sync + Send>, transform: Transform) -> Option<Self> {
+        let inverse = transform.inverse()?;
+        // around the corners of the object's box wherever they end up
+        let bounds = object.bounding_box().map(|b| {
+            let corners = (0..8).map(|i| {
+                let pick = |bit: usize, k: usize| if i & bit == 0 { b.min[k] } else { b.max[k] };
+                transform.point(&Point3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+            });
+            corners
+                .map(|p| Aabb::new(p, p))
+                .reduce(|a, b| a.surrounding(&b))
+                .unwrap()
+        });
+        Some(Self {
+            object,
+            transform,
+            inverse,
+            material: None,
+            bounds,
+        })
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    // the ray in the object's own space, with the same t for the same points
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.point(&ray.origin),
+            self.inverse.vector(&ray.dir),
+        )
+        .with_wavelength(ray.wavelength)
+    }
+
+    fn to_world<'a>(&'a self, ray: &Ray, record: &mut HitRecord<'a>) {
+        record.p = ray.at(record.t);
+        let bitangent = record.normal.cross(&record.tangent);
+        record.normal = self.inverse.normal(&record.normal).unit_vector();
+        let tangent = self.transform.vector(&record.tangent);
+        record.dp_du *= tangent.len();
+        record.dp_dv *= self.transform.vector(&bitangent).len();
+        record.tangent = tangent.unit_vector();
+        if let Some(material) = &self.material {
+            record.material = Cow::Borrowed(material);
+        }
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64, record: &mut HitRecord<'a>) -> bool {
+        if !self.object.hit(&self.local_ray(ray), t_min, t_max, record) {
+            return false;
+        }
+        self.to_world(ray, record);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval<'_>>> {
+        let mut intervals = self.object.intervals(&self.local_ray(ray))?;
+        for interval in &mut intervals {
+            self.to_world(ray, &mut interval.enter);
+            self.to_world(ray, &mut interval.exit);
+        }
+        Some(intervals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::{Color, Vec3};
+    use crate::object::Sphere;
+
+    #[test]
+    fn test_hit() {
+        // a unit sphere stretched into an ellipsoid and moved up
+        let transform = Transform::translation(&Vec3::new(0.0, 2.0, 0.0))
+            * Transform::scaling(&Vec3::new(3.0, 1.0, 1.0));
+        let sphere = Arc::new(Sphere::new(Point3::default(), 1.0, Material::default()));
+        let ellipsoid = Transformed::new(sphere, transform).unwrap();
+        let bounds = ellipsoid.bounding_box().unwrap();
+        assert!((bounds.min - Point3::new(-3.0, 1.0, -1.0)).len() < 1e-12);
+        assert!((bounds.max - Point3::new(3.0, 3.0, 1.0)).len() < 1e-12);
+
+        let mut record = HitRecord::default();
+        let ray = Ray::new(Point3::new(-10.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(ellipsoid.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!((record.t - 7.0).abs() < 1e-12);
+        assert!((record.p - Point3::new(-3.0, 2.0, 0.0)).len() < 1e-12);
+        assert!((record.normal - Vec3::new(-1.0, 0.0, 0.0)).len() < 1e-12);
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!ellipsoid.hit(&ray, 0.001, f64::INFINITY, &mut record));
+    }
+
+    #[test]
+    fn test_material() {
+        let sphere = Arc::new(Sphere::new(Point3::default(), 1.0, Material::default()));
+        let red = Color::new(1.0, 0.0, 0.0);
+        let object = Transformed::new(sphere, Transform::identity())
+            .unwrap()
+            .with_material(Material::Lambertian { albedo: red });
+        let mut record = HitRecord::default();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(object.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!(matches!(*record.material, Material::Lambertian { albedo } if albedo == red));
+        assert!(
+            Transformed::new(object.object, Transform::scaling(&Vec3::new(1.0, 0.0, 1.0)))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::euclidean::{Point3, Ray};
+
+// Möller–Trumbore ray triangle intersection, returning the ray parameter and
+// the barycentric coordinates of the hit relative to p1 and p2.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    p0: &Point3,
+    p1: &Point3,
+    p2: &Point3,
+) -> Option<(f64, f64, f64)> {
+    let e1 = *p1 - *p0;
+    let e2 = *p2 - *p0;
+    let pvec = ray.dir.cross(&e2);
+    let det = e1.dot(&pvec);
+    if det.abs() < 1e-12 {
+        // parallel to the triangle
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = ray.origin - *p0;
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(&e1);
+    let b2 = ray.dir.dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    Some((e2.dot(&qvec) * inv_det, b1, b2))
+}
@@ -0,0 +1,437 @@
+use super::json::{deserialize_object, serialize_object};
+use super::Scene;
+use crate::camera::{sort_keys, CameraPath, Interpolation};
+use crate::euclidean::{degrees_to_rads, Transform, Vec3};
+use crate::hittable::{Hittable, HittableList};
+use crate::material::Material;
+use crate::object::Transformed;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+// Placement of an animated object at a moment in time, in seconds: where its
+// origin is moved to, its rotation in degrees about x, then y, then z, and
+// its scale along each axis.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransformKey {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: Vec3,
+}
+
+impl TransformKey {
+    pub fn transform(&self) -> Transform {
+        let rotate =
+            |axis: Vec3, degrees: f64| Transform::rotation_about(&axis, degrees_to_rads(degrees));
+        Transform::translation(&self.translation)
+            * rotate(Vec3::new(0.0, 0.0, 1.0), self.rotation.z())
+            * rotate(Vec3::new(0.0, 1.0, 0.0), self.rotation.y())
+            * rotate(Vec3::new(1.0, 0.0, 0.0), self.rotation.x())
+            * Transform::scaling(&self.scale)
+    }
+}
+
+// The material of an animated object from a moment in time, in seconds.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MaterialKey {
+    pub time: f64,
+    pub material: Material,
+}
+
+// An object that moves or changes material over an animation. Without
+// transform keys it stays where it is, and without material keys it keeps
+// its own material.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "SavedAnimatedObject")]
+pub struct AnimatedObject {
+    #[serde(serialize_with = "serialize_object")]
+    object: Arc<dyn Hittable + Sync + Send>,
+    // each in order of time, with no two at the same time
+    transform_keys: Vec<TransformKey>,
+    material_keys: Vec<MaterialKey>,
+    // how the transform keys are interpolated; materials always blend
+    // linearly from one key to the next
+    interpolation: Interpolation,
+}
+
+// the fields of an animated object that are saved, with its keys in any order
+#[derive(Deserialize)]
+struct SavedAnimatedObject {
+    #[serde(deserialize_with = "deserialize_object")]
+    object: Arc<dyn Hittable + Sync + Send>,
+    #[serde(default)]
+    transform_keys: Vec<TransformKey>,
+    #[serde(default)]
+    material_keys: Vec<MaterialKey>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+impl TryFrom<SavedAnimatedObject> for AnimatedObject {
+    type Error = String;
+
+    fn try_from(mut saved: SavedAnimatedObject) -> Result<Self, Self::Error> {
+        sort_keys(&mut saved.transform_keys, |k| k.time)?;
+        sort_keys(&mut saved.material_keys, |k| k.time)?;
+        Ok(Self {
+            object: saved.object,
+            transform_keys: saved.transform_keys,
+            material_keys: saved.material_keys,
+            interpolation: saved.interpolation,
+        })
+    }
+}
+
+// what decides how an animated object looks at a moment: its transform, and
+// the material key it has reached and how far it is towards the next one
+#[derive(Clone, PartialEq)]
+struct Pose {
+    transform: Transform,
+    material: Option<(usize, f64)>,
+}
+
+impl AnimatedObject {
+    pub fn new(object: Arc<dyn Hittable + Sync + Send>, interpolation: Interpolation) -> Self {
+        Self {
+            object,
+            transform_keys: Vec::new(),
+            material_keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    // Keys can be added in any order, and replace any key at the same time.
+    pub fn with_transform_key(mut self, key: TransformKey) -> Self {
+        assert!(key.time.is_finite(), "key times must be finite");
+        let i = self.transform_keys.partition_point(|k| k.time < key.time);
+        match self.transform_keys.get_mut(i) {
+            Some(k) if k.time == key.time => *k = key,
+            _ => self.transform_keys.insert(i, key),
+        }
+        self
+    }
+
+    pub fn with_material_key(mut self, key: MaterialKey) -> Self {
+        assert!(key.time.is_finite(), "key times must be finite");
+        let i = self.material_keys.partition_point(|k| k.time < key.time);
+        match self.material_keys.get_mut(i) {
+            Some(k) if k.time == key.time => *k = key,
+            _ => self.material_keys.insert(i, key),
+        }
+        self
+    }
+
+    fn pose(&self, time: f64) -> Pose {
+        let keys = &self.transform_keys;
+        let times: Vec<f64> = keys.iter().map(|k| k.time).collect();
+        let at = |values: Vec<Vec3>| self.interpolation.at(&times, &values, time);
+        let transform = match (
+            at(keys.iter().map(|k| k.translation).collect()),
+            at(keys.iter().map(|k| k.rotation).collect()),
+            at(keys.iter().map(|k| k.scale).collect()),
+        ) {
+            (Some(translation), Some(rotation), Some(scale)) => TransformKey {
+                time,
+                translation,
+                rotation,
+                scale,
+            }
+            .transform(),
+            // without any keys
+            _ => Transform::identity(),
+        };
+
+        let keys = &self.material_keys;
+        let material = if keys.is_empty() {
+            None
+        } else if time <= keys[0].time {
+            Some((0, 0.0))
+        } else {
+            let i = keys.partition_point(|k| k.time <= time) - 1;
+            match keys.get(i + 1) {
+                Some(next) => Some((i, (time - keys[i].time) / (next.time - keys[i].time))),
+                None => Some((i, 0.0)),
+            }
+        };
+        Pose {
+            transform,
+            material,
+        }
+    }
+
+    // None if the object is scaled away to nothing
+    fn build(&self, pose: &Pose) -> Option<Arc<dyn Hittable + Sync + Send>> {
+        let transformed = Transformed::new(self.object.clone(), pose.transform)?;
+        let transformed = match pose.material {
+            Some((i, s)) if s > 0.0 => transformed.with_material(blend(
+                &self.material_keys[i].material,
+                &self.material_keys[i + 1].material,
+                s,
+            )),
+            Some((i, _)) => transformed.with_material(self.material_keys[i].material.clone()),
+            None => transformed,
+        };
+        Some(Arc::new(transformed))
+    }
+}
+
+// Blends the numeric parameters of two materials of the same kind, the
+// fraction s of the way from a to b. Materials of different kinds, or with
+// nothing to blend, stay as a until b is reached.
+fn blend(a: &Material, b: &Material, s: f64) -> Material {
+    let mix = |x: f64, y: f64| x + (y - x) * s;
+    match (a, b) {
+        (Material::Lambertian { albedo: x }, Material::Lambertian { albedo: y }) => {
+            Material::Lambertian {
+                albedo: *x + (*y - *x) * s,
+            }
+        }
+        (
+            Material::Metal {
+                albedo: x,
+                fuzz_in: fx,
+            },
+            Material::Metal {
+                albedo: y,
+                fuzz_in: fy,
+            },
+        ) => Material::Metal {
+            albedo: *x + (*y - *x) * s,
+            fuzz_in: mix(*fx, *fy),
+        },
+        (Material::Dialectric { ir: x }, Material::Dialectric { ir: y }) => {
+            Material::Dialectric { ir: mix(*x, *y) }
+        }
+        (Material::Coated { base: x, ir: ix }, Material::Coated { base: y, ir: iy }) => {
+            Material::Coated {
+                base: Arc::new(blend(x, y, s)),
+                ir: mix(*ix, *iy),
+            }
+        }
+        (
+            Material::ThinFilm {
+                ir: ix,
+                thickness: tx,
+            },
+            Material::ThinFilm {
+                ir: iy,
+                thickness: ty,
+            },
+        ) => Material::ThinFilm {
+            ir: mix(*ix, *iy),
+            thickness: mix(*tx, *ty),
+        },
+        (Material::DiffuseLight { emit: x }, Material::DiffuseLight { emit: y }) => {
+            Material::DiffuseLight {
+                emit: *x + (*y - *x) * s,
+            }
+        }
+        _ => a.clone(),
+    }
+}
+
+// Keyframed changes to a scene: the path the camera follows and the objects
+// that move, alongside the still ones in the scene's world.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Animation {
+    #[serde(default)]
+    pub camera: CameraPath,
+    #[serde(default)]
+    pub objects: Vec<AnimatedObject>,
+}
+
+// The world of a scene at one moment of its animation.
+#[derive(Default)]
+pub struct Frame {
+    pub world: HittableList,
+    poses: Vec<Pose>,
+    objects: Vec<Option<Arc<dyn Hittable + Sync + Send>>>,
+    // whether the world has been filled in yet
+    built: bool,
+}
+
+impl Frame {
+    // Moves the frame on to a time in the scene's animation. Only animated
+    // objects that look different then are rebuilt, and the world only if
+    // any are.
+    pub fn update(&mut self, scene: &Scene, time: f64) {
+        let animated = &scene.animation.objects;
+        let fresh = !self.built || self.poses.len() != animated.len();
+        let mut changed = fresh;
+        if fresh {
+            self.poses.clear();
+            self.objects.clear();
+        }
+        for (i, object) in animated.iter().enumerate() {
+            let pose = object.pose(time);
+            if fresh {
+                self.objects.push(object.build(&pose));
+                self.poses.push(pose);
+            } else if pose != self.poses[i] {
+                self.objects[i] = object.build(&pose);
+                self.poses[i] = pose;
+                changed = true;
+            }
+        }
+        if changed {
+            self.built = true;
+            self.world = HittableList::default();
+            for object in scene.world.iter().chain(self.objects.iter().flatten()) {
+                self.world.add(object.clone());
+            }
+        }
+    }
+}
+
+impl Scene {
+    // the world as it is at a time in the scene's animation
+    pub fn frame(&self, time: f64) -> Frame {
+        let mut frame = Frame::default();
+        frame.update(self, time);
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::{Color, Point3, Ray};
+    use crate::hittable::HitRecord;
+    use crate::object::Sphere;
+
+    fn bouncing_ball() -> AnimatedObject {
+        let ball = Arc::new(Sphere::new(Point3::default(), 1.0, Material::default()));
+        let key = |time: f64, height: f64| TransformKey {
+            time,
+            translation: Vec3::new(0.0, height, 0.0),
+            rotation: Vec3::default(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        };
+        let color = |time: f64, red: f64| MaterialKey {
+            time,
+            material: Material::Lambertian {
+                albedo: Color::new(red, 0.0, 0.0),
+            },
+        };
+        AnimatedObject::new(ball, Interpolation::Linear)
+            .with_transform_key(key(0.0, 4.0))
+            .with_transform_key(key(1.0, 1.0))
+            .with_transform_key(key(2.0, 4.0))
+            .with_material_key(color(0.0, 0.0))
+            .with_material_key(color(1.0, 1.0))
+    }
+
+    // where a ray down the y axis first hits the world, and its red
+    fn hit_from_above(world: &HittableList) -> Option<(f64, f64)> {
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        if !world.hit(&ray, 0.001, f64::INFINITY, &mut record) {
+            return None;
+        }
+        match *record.material {
+            Material::Lambertian { albedo } => Some((record.p.y(), albedo.x())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut scene = Scene::default();
+        scene.animation.objects.push(bouncing_ball());
+        let mut frame = scene.frame(0.0);
+        let (y, red) = hit_from_above(&frame.world).unwrap();
+        assert!((y - 5.0).abs() < 1e-9 && red.abs() < 1e-12);
+        frame.update(&scene, 0.5);
+        let (y, red) = hit_from_above(&frame.world).unwrap();
+        assert!((y - 3.5).abs() < 1e-9 && (red - 0.5).abs() < 1e-12);
+        // the color holds after the last material key
+        frame.update(&scene, 2.0);
+        let (y, red) = hit_from_above(&frame.world).unwrap();
+        assert!((y - 5.0).abs() < 1e-9 && (red - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_still_scene() {
+        let mut scene = Scene::default();
+        scene.world.add(Arc::new(Sphere::new(
+            Point3::default(),
+            1.0,
+            Material::Lambertian {
+                albedo: Color::new(1.0, 0.0, 0.0),
+            },
+        )));
+        assert_eq!(hit_from_above(&scene.frame(0.0).world), Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_unchanged_objects_reused() {
+        let mut scene = Scene::default();
+        scene.world.add(Arc::new(Sphere::new(
+            Point3::new(5.0, 0.0, 0.0),
+            1.0,
+            Material::default(),
+        )));
+        scene.animation.objects.push(bouncing_ball());
+        // a ball that stays put
+        scene.animation.objects.push(AnimatedObject::new(
+            Arc::new(Sphere::new(Point3::default(), 1.0, Material::default())),
+            Interpolation::Linear,
+        ));
+        let mut frame = scene.frame(3.0);
+        assert_eq!(frame.world.iter().count(), 3);
+        let before: Vec<_> = frame.objects.iter().flatten().cloned().collect();
+        frame.update(&scene, 4.0);
+        let after: Vec<_> = frame.objects.iter().flatten().cloned().collect();
+        assert!(Arc::ptr_eq(&before[0], &after[0]));
+        assert!(Arc::ptr_eq(&before[1], &after[1]));
+        frame.update(&scene, 1.5);
+        assert!(!Arc::ptr_eq(&before[0], &frame.objects[0].clone().unwrap()));
+        assert!(Arc::ptr_eq(&before[1], &frame.objects[1].clone().unwrap()));
+    }
+
+    #[test]
+    fn test_saved() {
+        let mut scene = Scene::default();
+        scene.animation.objects.push(bouncing_ball());
+        scene.animation.camera = CameraPath::turntable(
+            &Point3::new(0.0, 2.0, 10.0),
+            &Point3::default(),
+            20.0,
+            10.0,
+            2.0,
+        );
+        let json = scene.to_json().unwrap();
+        let loaded = Scene::parse_json(&json).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert_eq!(loaded.animation.camera, scene.animation.camera);
+        assert_eq!(
+            hit_from_above(&loaded.frame(0.5).world),
+            hit_from_above(&scene.frame(0.5).world)
+        );
+        // scenes saved before animations load without one
+        let still = Scene::parse_json(r#"{"world": [], "cameras": []}"#).unwrap();
+        assert!(still.animation.objects.is_empty() && still.animation.camera.is_empty());
+    }
+
+    #[test]
+    fn test_saved_keys_out_of_order() {
+        let mut scene = Scene::default();
+        scene.animation.objects.push(bouncing_ball());
+        let mut json: serde_json::Value = serde_json::from_str(&scene.to_json().unwrap()).unwrap();
+        let object = &mut json["animation"]["objects"][0];
+        object["transform_keys"].as_array_mut().unwrap().reverse();
+        object["material_keys"].as_array_mut().unwrap().reverse();
+        let loaded = Scene::parse_json(&json.to_string()).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), scene.to_json().unwrap());
+        for time in [0.0, 0.5, 1.5, 2.0] {
+            assert_eq!(
+                hit_from_above(&loaded.frame(time).world),
+                hit_from_above(&scene.frame(time).world)
+            );
+        }
+
+        json["animation"]["objects"][0]["transform_keys"][0]["time"] = serde_json::json!(1.0);
+        assert!(Scene::parse_json(&json.to_string()).is_err());
+    }
+}
@@ -0,0 +1,905 @@
+use super::Scene;
+use crate::camera::{Camera, Projection};
+use crate::euclidean::{Color, Point3, Transform, Vec3};
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::object::TriangleMesh;
+use crate::texture::{Image, Texture};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+// used for cameras that leave their aspect ratio up to the viewer
+const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+// glTF primitive mode for triangle lists
+const TRIANGLES: u32 = 4;
+
+// most elements an accessor without a buffer view may ask to be filled with
+// zeros, which would otherwise let a tiny file allocate without limit
+const MAX_ZEROED_ELEMENTS: usize = 1 << 24;
+
+impl Scene {
+    // Loads a glTF 2.0 scene, either a .gltf file with its buffers and
+    // images next to it or embedded as data URIs, or a binary .glb file.
+    pub fn load_gltf<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse_gltf(&fs::read(path)?, base)
+    }
+
+    // Builds the default scene of a glTF file, resolving relative URIs against
+    // the base directory. Mesh primitives become triangle meshes with their
+    // node transforms applied, emissive materials become lights and
+    // metallic-roughness ones are approximated by a metal or a diffuse
+    // surface, and perspective and orthographic cameras are kept.
+    // Points, lines and animations are ignored.
+    pub fn parse_gltf(bytes: &[u8], base: &Path) -> io::Result<Self> {
+        let (json, binary) = if bytes.starts_with(b"glTF") {
+            split_glb(bytes)?
+        } else {
+            (bytes, None)
+        };
+        let document: Document =
+            serde_json::from_slice(json).map_err(|e| invalid(&e.to_string()))?;
+        let buffers = document
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| match &buffer.uri {
+                Some(uri) => load_uri(uri, base),
+                None if i == 0 => binary
+                    .map(|b| b.to_vec())
+                    .ok_or_else(|| invalid("missing glb binary chunk")),
+                None => Err(invalid("buffer without data")),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let loader = Loader {
+            document: &document,
+            buffers,
+            base,
+        };
+        let materials = document
+            .materials
+            .iter()
+            .map(|m| loader.material(m))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut scene = Scene::default();
+        let roots = match document.scenes.get(document.scene.unwrap_or(0)) {
+            Some(s) => s.nodes.clone(),
+            // without scenes every node that isn't a child is a root
+            None => {
+                let children: Vec<usize> = document
+                    .nodes
+                    .iter()
+                    .flat_map(|n| n.children.clone())
+                    .collect();
+                (0..document.nodes.len())
+                    .filter(|i| !children.contains(i))
+                    .collect()
+            }
+        };
+        let mut stack: Vec<(usize, Transform)> = roots
+            .into_iter()
+            .map(|n| (n, Transform::identity()))
+            .collect();
+        // nodes form disjoint trees, so any node reached twice is in a cycle
+        // or shared between parents
+        let mut visited = vec![false; document.nodes.len()];
+        while let Some((index, parent)) = stack.pop() {
+            let node = document
+                .nodes
+                .get(index)
+                .ok_or_else(|| invalid("missing node"))?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(invalid("node cycle"));
+            }
+            let transform = parent * node.transform();
+            if let Some(mesh) = node.mesh {
+                loader.add_mesh(mesh, &transform, &materials, &mut scene)?;
+            }
+            if let Some(camera) = node.camera {
+                scene.cameras.push(loader.camera(camera, &transform)?);
+            }
+            stack.extend(node.children.iter().map(|c| (*c, transform)));
+        }
+        Ok(scene)
+    }
+}
+
+struct Loader<'a> {
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+    base: &'a Path,
+}
+
+impl Loader<'_> {
+    fn view_bytes(&self, view: &BufferView) -> io::Result<&[u8]> {
+        let buffer = self
+            .buffers
+            .get(view.buffer)
+            .ok_or_else(|| invalid("missing buffer"))?;
+        view.byte_offset
+            .checked_add(view.byte_length)
+            .and_then(|end| buffer.get(view.byte_offset..end))
+            .ok_or_else(|| invalid("buffer view out of range"))
+    }
+
+    // values of an accessor of the given type, with the components of each
+    // element in a row
+    fn accessor(&self, index: usize, kind: &str) -> io::Result<Vec<Vec<f64>>> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .ok_or_else(|| invalid("missing accessor"))?;
+        if accessor.kind != kind {
+            return Err(invalid(&format!("expected a {} accessor", kind)));
+        }
+        if accessor.sparse.is_some() {
+            return Err(invalid("sparse accessors are not supported"));
+        }
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            _ => return Err(invalid("unknown accessor type")),
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(invalid("unknown accessor component type")),
+        };
+        let view = match accessor.buffer_view {
+            Some(view) => self
+                .document
+                .buffer_views
+                .get(view)
+                .ok_or_else(|| invalid("missing buffer view"))?,
+            // no view means all zeros
+            None if accessor.count > MAX_ZEROED_ELEMENTS => {
+                return Err(invalid("accessor too large"))
+            }
+            None => return Ok(vec![vec![0.0; components]; accessor.count]),
+        };
+        let data = self.view_bytes(view)?;
+        let stride = view.byte_stride.unwrap_or(components * size);
+        // elements may not overlap, which bounds the count by the view's size
+        if stride < components * size {
+            return Err(invalid("buffer view stride smaller than its elements"));
+        }
+
+        let component = |bytes: &[u8]| -> f64 {
+            let (value, max) = match accessor.component_type {
+                5120 => (bytes[0] as i8 as f64, i8::MAX as f64),
+                5121 => (bytes[0] as f64, u8::MAX as f64),
+                5122 => (
+                    i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    i16::MAX as f64,
+                ),
+                5123 => (
+                    u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    u16::MAX as f64,
+                ),
+                5125 => (
+                    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                    u32::MAX as f64,
+                ),
+                _ => (
+                    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                    1.0,
+                ),
+            };
+            if accessor.normalized {
+                (value / max).max(-1.0)
+            } else {
+                value
+            }
+        };
+        (0..accessor.count)
+            .map(|i| {
+                let element = i
+                    .checked_mul(stride)
+                    .and_then(|offset| offset.checked_add(accessor.byte_offset))
+                    .and_then(|start| data.get(start..start.checked_add(components * size)?))
+                    .ok_or_else(|| invalid("accessor out of range"))?;
+                Ok(element.chunks(size).map(component).collect())
+            })
+            .collect()
+    }
+
+    // Emissive materials become lights. Others are a metal or a diffuse
+    // surface depending on how metallic they are, with their metallic and
+    // roughness textures averaged into the factors since neither varies over
+    // a surface here.
+    fn material(&self, material: &MaterialDef) -> io::Result<Material> {
+        let [r, g, b] = material.emissive_factor;
+        let mut emit = Color::new(r, g, b);
+        if let Some(texture) = &material.emissive_texture {
+            // emissive textures are stored in sRGB
+            emit = emit * average(&self.image(texture.index)?.srgb_to_linear());
+        }
+        if emit.len() > 0.0 {
+            return Ok(Material::DiffuseLight { emit });
+        }
+
+        let pbr = &material.pbr_metallic_roughness;
+        let [r, g, b, _] = pbr.base_color_factor;
+        let albedo = Color::new(r, g, b);
+        let (mut metallic, mut roughness) = (pbr.metallic_factor, pbr.roughness_factor);
+        if let Some(texture) = &pbr.metallic_roughness_texture {
+            // roughness is in the green channel and metalness in the blue
+            let mean = average(&self.image(texture.index)?);
+            roughness *= mean.y();
+            metallic *= mean.z();
+        }
+        let base = if metallic >= 0.5 {
+            Material::Metal {
+                albedo,
+                fuzz_in: roughness,
+            }
+        } else {
+            Material::Lambertian { albedo }
+        };
+        let texture = match &pbr.base_color_texture {
+            Some(t) => t.index,
+            None => return Ok(base),
+        };
+        // color textures are stored in sRGB
+        let image = self.image(texture)?.srgb_to_linear();
+        Ok(Material::Textured {
+            base: Arc::new(base),
+            albedo: Texture::Image(Arc::new(image)),
+        })
+    }
+
+    // the image of a texture, as stored
+    fn image(&self, texture: usize) -> io::Result<Image> {
+        let image = self
+            .document
+            .textures
+            .get(texture)
+            .and_then(|t| t.source)
+            .and_then(|s| self.document.images.get(s))
+            .ok_or_else(|| invalid("missing texture image"))?;
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) => load_uri(uri, self.base)?,
+            (None, Some(view)) => {
+                let view = self
+                    .document
+                    .buffer_views
+                    .get(view)
+                    .ok_or_else(|| invalid("missing buffer view"))?;
+                self.view_bytes(view)?.to_vec()
+            }
+            (None, None) => return Err(invalid("image without data")),
+        };
+        Image::parse(&bytes)
+    }
+
+    fn add_mesh(
+        &self,
+        index: usize,
+        transform: &Transform,
+        materials: &[Material],
+        scene: &mut Scene,
+    ) -> io::Result<()> {
+        let mesh = self
+            .document
+            .meshes
+            .get(index)
+            .ok_or_else(|| invalid("missing mesh"))?;
+        for primitive in mesh.primitives.iter().filter(|p| p.mode == TRIANGLES) {
+            let position = *primitive
+                .attributes
+                .get("POSITION")
+                .ok_or_else(|| invalid("primitive without positions"))?;
+            let vertices: Vec<Point3> = self
+                .accessor(position, "VEC3")?
+                .iter()
+                .map(|p| transform.point(&Point3::new(p[0], p[1], p[2])))
+                .collect();
+            let indices: Vec<usize> = match primitive.indices {
+                Some(indices) => self
+                    .accessor(indices, "SCALAR")?
+                    .iter()
+                    .map(|i| match i[0] {
+                        i if i >= 0.0 && i < vertices.len() as f64 && i.fract() == 0.0 => {
+                            Ok(i as usize)
+                        }
+                        _ => Err(invalid("index out of range")),
+                    })
+                    .collect::<io::Result<_>>()?,
+                None => (0..vertices.len()).collect(),
+            };
+            let faces = indices.chunks_exact(3).map(|f| f.to_vec()).collect();
+            let mut mesh = Mesh::new(vertices, faces);
+            if let Some(uvs) = primitive.attributes.get("TEXCOORD_0") {
+                // glTF puts v = 0 at the top of images
+                let uvs: Vec<(f64, f64)> = self
+                    .accessor(*uvs, "VEC2")?
+                    .iter()
+                    .map(|uv| (uv[0], 1.0 - uv[1]))
+                    .collect();
+                if uvs.len() != mesh.vertices.len() {
+                    return Err(invalid("texture coordinates don't match positions"));
+                }
+                mesh = mesh.with_uvs(uvs);
+            }
+
+            let material = match primitive.material {
+                Some(m) => materials
+                    .get(m)
+                    .cloned()
+                    .ok_or_else(|| invalid("missing material"))?,
+                // plain gray like most viewers use
+                None => Material::Lambertian {
+                    albedo: Color::new(0.8, 0.8, 0.8),
+                },
+            };
+            scene
+                .world
+                .add(Arc::new(TriangleMesh::new(&mesh, material)));
+        }
+        Ok(())
+    }
+
+    // glTF cameras look down their local -z axis with +y up
+    fn camera(&self, index: usize, transform: &Transform) -> io::Result<Camera> {
+        let camera = self
+            .document
+            .cameras
+            .get(index)
+            .ok_or_else(|| invalid("missing camera"))?;
+        let look_from = transform.point(&Point3::default());
+        let forward = transform.vector(&Vec3::new(0.0, 0.0, -1.0));
+        let up = transform.vector(&Vec3::new(0.0, 1.0, 0.0));
+        let looking = |vertical_fov: f64, aspect_ratio: f64| {
+            Camera::new(
+                &look_from,
+                &(look_from + forward),
+                &up,
+                vertical_fov,
+                aspect_ratio,
+                0.0,
+                1.0,
+            )
+        };
+        match (
+            camera.kind.as_str(),
+            &camera.perspective,
+            &camera.orthographic,
+        ) {
+            ("perspective", Some(p), _) => Ok(looking(
+                p.yfov * 180.0 / PI,
+                p.aspect_ratio.unwrap_or(DEFAULT_ASPECT_RATIO),
+            )),
+            // the magnifications are half the width and height of the view
+            ("orthographic", _, Some(o)) if o.xmag > 0.0 && o.ymag > 0.0 => {
+                Ok(
+                    looking(90.0, o.xmag / o.ymag).with_projection(Projection::Orthographic {
+                        height: 2.0 * o.ymag,
+                    }),
+                )
+            }
+            ("orthographic", _, Some(_)) => Err(invalid("orthographic camera without a size")),
+            ("perspective", None, _) | ("orthographic", _, None) => {
+                Err(invalid("camera without its projection"))
+            }
+            _ => Err(invalid("unsupported camera type")),
+        }
+    }
+}
+
+fn average(image: &Image) -> Color {
+    let mut sum = Color::default();
+    for j in 0..image.height() {
+        for i in 0..image.width() {
+            sum += image.pixel(i, j);
+        }
+    }
+    sum / (image.width() * image.height()) as f64
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// splits a binary glTF file into its JSON and binary chunks
+fn split_glb(bytes: &[u8]) -> io::Result<(&[u8], Option<&[u8]>)> {
+    let word = |at: usize| -> io::Result<usize> {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| invalid("truncated glb"))
+    };
+    if word(4)? != 2 {
+        return Err(invalid("unsupported glb version"));
+    }
+    let mut chunks = HashMap::new();
+    let mut at = 12;
+    while at + 8 <= bytes.len().min(word(8)?) {
+        let length = word(at)?;
+        let kind = word(at + 4)?;
+        let data = bytes
+            .get(at + 8..at + 8 + length)
+            .ok_or_else(|| invalid("truncated glb chunk"))?;
+        chunks.entry(kind).or_insert(data);
+        at += 8 + length;
+    }
+    let json = chunks
+        .get(&0x4e4f_534a)
+        .ok_or_else(|| invalid("glb without json chunk"))?;
+    Ok((json, chunks.get(&0x004e_4942).copied()))
+}
+
+fn load_uri(uri: &str, base: &Path) -> io::Result<Vec<u8>> {
+    match uri.strip_prefix("data:") {
+        Some(data) => {
+            let (_, encoded) = data
+                .split_once(";base64,")
+                .ok_or_else(|| invalid("data uri is not base64"))?;
+            decode_base64(encoded)
+        }
+        None => fs::read(base.join(uri.replace("%20", " "))),
+    }
+}
+
+fn decode_base64(encoded: &str) -> io::Result<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Ok(c - b'A'),
+        b'a'..=b'z' => Ok(c - b'a' + 26),
+        b'0'..=b'9' => Ok(c - b'0' + 52),
+        b'+' | b'-' => Ok(62),
+        b'/' | b'_' => Ok(63),
+        _ => Err(invalid("invalid base64")),
+    };
+    let digits = encoded
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+        .map(value)
+        .collect::<io::Result<Vec<u8>>>()?;
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, d)| acc | (*d as u32) << (18 - 6 * i));
+        bytes.extend(bits.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Ok(bytes)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    textures: Vec<TextureDef>,
+    #[serde(default)]
+    images: Vec<ImageDef>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<Buffer>,
+    #[serde(default)]
+    cameras: Vec<CameraDef>,
+}
+
+#[derive(Deserialize)]
+struct SceneDef {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    camera: Option<usize>,
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f64; 16]>,
+    translation: Option<[f64; 3]>,
+    rotation: Option<[f64; 4]>,
+    scale: Option<[f64; 3]>,
+}
+
+impl Node {
+    fn transform(&self) -> Transform {
+        if let Some(matrix) = &self.matrix {
+            return Transform::from_columns(matrix);
+        }
+        let [tx, ty, tz] = self.translation.unwrap_or([0.0; 3]);
+        let [sx, sy, sz] = self.scale.unwrap_or([1.0; 3]);
+        Transform::translation(&Vec3::new(tx, ty, tz))
+            * Transform::rotation(&self.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]))
+            * Transform::scaling(&Vec3::new(sx, sy, sz))
+    }
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<Primitive>,
+}
+
+fn default_mode() -> u32 {
+    TRIANGLES
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "default_mode")]
+    mode: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef {
+    #[serde(default)]
+    pbr_metallic_roughness: Pbr,
+    #[serde(default)]
+    emissive_factor: [f64; 3],
+    emissive_texture: Option<TextureRef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Pbr {
+    base_color_factor: [f64; 4],
+    base_color_texture: Option<TextureRef>,
+    metallic_factor: f64,
+    roughness_factor: f64,
+    metallic_roughness_texture: Option<TextureRef>,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Self {
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TextureRef {
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct TextureDef {
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageDef {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Buffer {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CameraDef {
+    #[serde(rename = "type")]
+    kind: String,
+    perspective: Option<Perspective>,
+    orthographic: Option<Orthographic>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Perspective {
+    yfov: f64,
+    aspect_ratio: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Orthographic {
+    xmag: f64,
+    ymag: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::Ray;
+    use crate::hittable::HitRecord;
+
+    // a triangle 5 units in front of a camera, packed into a .glb
+    fn glb() -> Vec<u8> {
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 1]}],
+            "nodes": [
+                {"mesh": 0, "translation": [0, 0, -5]},
+                {"camera": 0, "translation": [0.25, 0.25, 0]}
+            ],
+            "meshes": [{"primitives": [
+                {"attributes": {"POSITION": 0}, "indices": 1, "material": 0}
+            ]}],
+            "materials": [{"pbrMetallicRoughness": {
+                "baseColorFactor": [0.5, 0.25, 0.125, 1.0], "metallicFactor": 0.0
+            }}],
+            "cameras": [{"type": "perspective", "perspective": {
+                "yfov": 0.8, "aspectRatio": 1.5, "znear": 0.1
+            }}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "buffers": [{"byteLength": 44}]
+        }"#;
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut binary: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        binary.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+        binary.extend([0, 0]);
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(b"JSON");
+        bytes.extend(json);
+        bytes.extend((binary.len() as u32).to_le_bytes());
+        bytes.extend(b"BIN\0");
+        bytes.extend(binary);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_glb() {
+        let scene = Scene::parse_gltf(&glb(), Path::new("")).unwrap();
+        assert_eq!(scene.cameras.len(), 1);
+
+        // the camera looks straight at the triangle
+        let ray = scene.cameras[0].get_ray(0.5, 0.5);
+        assert!((ray.origin - Point3::new(0.25, 0.25, 0.0)).len() < 1e-12);
+        let mut record = HitRecord::default();
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!((record.t * ray.dir.len() - 5.0).abs() < 1e-9);
+        assert!(matches!(
+            *record.material,
+            Material::Lambertian { albedo } if albedo == Color::new(0.5, 0.25, 0.125)
+        ));
+
+        let beside = Ray::new(Point3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!scene.world.hit(&beside, 0.001, f64::INFINITY, &mut record));
+    }
+
+    // an emissive triangle 5 units in front of a camera with the given
+    // settings, with its positions in a data URI
+    fn emissive(camera: &str) -> io::Result<Scene> {
+        let json = format!(
+            r#"{{
+                "nodes": [
+                    {{"mesh": 0, "translation": [0, 0, -5]}},
+                    {{"camera": 0}}
+                ],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+                "materials": [{{"emissiveFactor": [1.0, 0.5, 0.0]}}],
+                "cameras": [{}],
+                "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3,
+                    "type": "VEC3"}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+                "buffers": [{{"uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}}]
+            }}"#,
+            camera
+        );
+        Scene::parse_gltf(json.as_bytes(), Path::new(""))
+    }
+
+    #[test]
+    fn test_emissive_and_orthographic() {
+        let scene = emissive(r#"{"type": "orthographic", "orthographic": {"xmag": 2, "ymag": 1}}"#)
+            .unwrap();
+        let camera = &scene.cameras[0];
+        let right = camera.get_ray(1.0, 0.5);
+        assert!((right.origin - Point3::new(2.0, 0.0, 0.0)).len() < 1e-12);
+        assert!((right.dir.unit_vector() - Vec3::new(0.0, 0.0, -1.0)).len() < 1e-12);
+
+        // parallel rays hit the triangle at (0.25, 0.25)
+        let ray = camera.get_ray(0.5 + 0.25 / 4.0, 0.5 + 0.25 / 2.0);
+        let mut record = HitRecord::default();
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!(matches!(
+            *record.material,
+            Material::DiffuseLight { emit } if emit == Color::new(1.0, 0.5, 0.0)
+        ));
+
+        let error = emissive(r#"{"type": "panoramic"}"#).err().unwrap();
+        assert_eq!(error.to_string(), "unsupported camera type");
+        let error = emissive(r#"{"type": "orthographic"}"#).err().unwrap();
+        assert_eq!(error.to_string(), "camera without its projection");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Scene::parse_gltf(b"{\"nodes\": [{\"mesh\": 3}]}", Path::new("")).is_err());
+        let mut truncated = glb();
+        truncated.truncate(40);
+        assert!(Scene::parse_gltf(&truncated, Path::new("")).is_err());
+
+        // accessors without buffer views read as zeros
+        let primitive = |attributes: &str, accessors: &str| {
+            let json = format!(
+                r#"{{
+                    "nodes": [{{"mesh": 0}}],
+                    "meshes": [{{"primitives": [{}]}}],
+                    "accessors": [{}]
+                }}"#,
+                attributes, accessors
+            );
+            Scene::parse_gltf(json.as_bytes(), Path::new(""))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        let positions = r#"{"componentType": 5126, "count": 3, "type": "VEC3"}"#;
+        assert_eq!(
+            primitive(
+                r#"{"attributes": {"POSITION": 0}}"#,
+                r#"{"componentType": 5126, "count": 3, "type": "SCALAR"}"#
+            ),
+            "expected a VEC3 accessor"
+        );
+        assert_eq!(
+            primitive(
+                r#"{"attributes": {"POSITION": 0}, "indices": 1}"#,
+                &format!(
+                    r#"{}, {{"componentType": 5123, "count": 3, "type": "VEC2"}}"#,
+                    positions
+                )
+            ),
+            "expected a SCALAR accessor"
+        );
+        assert_eq!(
+            primitive(
+                r#"{"attributes": {"POSITION": 0, "TEXCOORD_0": 1}}"#,
+                &format!(
+                    r#"{}, {{"componentType": 5126, "count": 2, "type": "VEC2"}}"#,
+                    positions
+                )
+            ),
+            "texture coordinates don't match positions"
+        );
+    }
+
+    #[test]
+    fn test_offsets_out_of_range() {
+        let json = |view: &str, offset: usize| {
+            let json = format!(
+                r#"{{
+                    "nodes": [{{"mesh": 0}}],
+                    "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+                    "accessors": [{{"bufferView": 0, "byteOffset": {}, "componentType": 5126,
+                        "count": 3, "type": "VEC3"}}],
+                    "bufferViews": [{}],
+                    "buffers": [{{"uri": "data:application/octet-stream;base64,AAAA"}}]
+                }}"#,
+                offset, view
+            );
+            Scene::parse_gltf(json.as_bytes(), Path::new(""))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        let max = usize::MAX;
+        assert_eq!(
+            json(
+                &format!(r#"{{"buffer": 0, "byteOffset": {}, "byteLength": 2}}"#, max),
+                0
+            ),
+            "buffer view out of range"
+        );
+        assert_eq!(
+            json(
+                r#"{"buffer": 0, "byteLength": 3, "byteStride": 12}"#,
+                max - 4
+            ),
+            "accessor out of range"
+        );
+        assert_eq!(
+            json(
+                &format!(r#"{{"buffer": 0, "byteLength": 3, "byteStride": {}}}"#, max),
+                0
+            ),
+            "accessor out of range"
+        );
+    }
+
+    #[test]
+    fn test_node_cycle() {
+        let json = br#"{
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"children": [1]}, {"children": [2]}, {"children": [0]}]
+        }"#;
+        let error = Scene::parse_gltf(json, Path::new("")).err().unwrap();
+        assert_eq!(error.to_string(), "node cycle");
+    }
+
+    #[test]
+    fn test_oversized_accessor() {
+        let json = br#"{
+            "nodes": [{"mesh": 0}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+            "accessors": [{"componentType": 5126, "count": 4000000000, "type": "VEC3"}]
+        }"#;
+        let error = Scene::parse_gltf(json, Path::new("")).err().unwrap();
+        assert_eq!(error.to_string(), "accessor too large");
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVsbG8h").unwrap(), b"hello!");
+        assert!(decode_base64("a*b").is_err());
+    }
+}
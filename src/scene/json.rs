@@ -0,0 +1,208 @@
+use super::Scene;
+use crate::hittable::{Hittable, HittableList};
+use crate::object::{Heightfield, Sphere};
+use serde::de::Deserializer;
+use serde::ser::{Error, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+impl Scene {
+    pub fn load_json<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse_json(&fs::read_to_string(path)?)
+    }
+
+    // Reads a scene written by to_json. Numbers are stored exactly, so a
+    // loaded scene renders the same as the one that was saved.
+    pub fn parse_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text).map_err(|e| invalid(&e.to_string()))
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json()?)
+    }
+
+    // Fails if the world holds objects that can't be saved yet.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+    }
+}
+
+// the kinds of object a scene file can hold, named as by Hittable::to_json
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SavedObject {
+    Sphere(Sphere),
+    Heightfield(Heightfield),
+}
+
+impl SavedObject {
+    fn into_object(self) -> Arc<dyn Hittable + Sync + Send> {
+        match self {
+            SavedObject::Sphere(sphere) => Arc::new(sphere),
+            SavedObject::Heightfield(heightfield) => Arc::new(heightfield),
+        }
+    }
+}
+
+// the object as it's listed in a world
+fn saved<E: Error>(object: &Arc<dyn Hittable + Sync + Send>) -> Result<Value, E> {
+    object
+        .to_json()
+        .ok_or_else(|| E::custom("the world holds an object that can't be saved"))
+}
+
+// Saves and loads single objects as they're listed in a world, for fields
+// holding an object outside of one.
+pub(super) fn serialize_object<S: Serializer>(
+    object: &Arc<dyn Hittable + Sync + Send>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    saved::<S::Error>(object)?.serialize(serializer)
+}
+
+pub(super) fn deserialize_object<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Arc<dyn Hittable + Sync + Send>, D::Error> {
+    Ok(SavedObject::deserialize(deserializer)?.into_object())
+}
+
+impl Serialize for HittableList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut objects = serializer.serialize_seq(None)?;
+        for object in self.iter() {
+            objects.serialize_element(&saved::<S::Error>(object)?)?;
+        }
+        objects.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HittableList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut world = HittableList::default();
+        for object in Vec::<SavedObject>::deserialize(deserializer)? {
+            world.add(object.into_object());
+        }
+        Ok(world)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::euclidean::{Color, Point3, Vec3};
+    use crate::hittable::HitRecord;
+    use crate::material::Material;
+    use crate::object::Disk;
+    use crate::scene::Animation;
+    use crate::spectrum::Dispersion;
+    use crate::texture::{SurfaceMap, Texture};
+
+    fn scene() -> Scene {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Heightfield::from_noise(
+            9,
+            7,
+            Point3::new(-5.0, -1.0, -5.0),
+            Vec3::new(10.0, 1.0, 10.0),
+            0.3,
+            3,
+            Material::Lambertian {
+                albedo: Color::new(0.5, 0.5, 0.5),
+            },
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.1, 0.7, 0.3),
+            0.7,
+            Material::DispersiveDialectric {
+                dispersion: Dispersion::BK7,
+            },
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(-2.0, 1.0, 0.0),
+            1.0 / 3.0,
+            Material::Mapped {
+                base: Arc::new(Material::Metal {
+                    albedo: Color::random(),
+                    fuzz_in: 0.1,
+                }),
+                map: SurfaceMap::Bump {
+                    height: Texture::Noise { scale: 8.0 },
+                    strength: 0.02,
+                },
+            },
+        )));
+        Scene {
+            world,
+            cameras: vec![Camera::new(
+                &Point3::new(13.0, 2.0, 3.0),
+                &Point3::new(0.0, 0.0, 0.0),
+                &Vec3::new(0.0, 1.0, 0.0),
+                20.0,
+                16.0 / 9.0,
+                0.0,
+                10.0,
+            )],
+            animation: Animation::default(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let scene = scene();
+        let json = scene.to_json().unwrap();
+        let loaded = Scene::parse_json(&json).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), json);
+
+        for (s, t) in [(0.5, 0.5), (0.3, 0.2), (0.55, 0.45)] {
+            let ray = scene.cameras[0].get_ray(s, t);
+            let loaded_ray = loaded.cameras[0].get_ray(s, t);
+            assert_eq!(ray.origin, loaded_ray.origin);
+            assert_eq!(ray.dir, loaded_ray.dir);
+
+            let mut record = HitRecord::default();
+            let mut loaded_record = HitRecord::default();
+            let hit = scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record);
+            assert_eq!(
+                loaded
+                    .world
+                    .hit(&ray, 0.001, f64::INFINITY, &mut loaded_record),
+                hit
+            );
+            assert_eq!(record.t, loaded_record.t);
+            assert_eq!(record.normal, loaded_record.normal);
+        }
+    }
+
+    #[test]
+    fn test_unsaved_object() {
+        let mut scene = scene();
+        scene.world.add(Arc::new(Disk::new(
+            Point3::default(),
+            Vec3::new(0.0, 1.0, 0.0),
+            1.0,
+            Material::default(),
+        )));
+        assert!(scene.to_json().is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Scene::parse_json("{\"world\": [").is_err());
+        assert!(Scene::parse_json("{\"world\": [{\"cone\": {}}], \"cameras\": []}").is_err());
+        let heightfield = r#"{"world": [{"heightfield": {"heights": [0, 0, 0], "nx": 2,
+            "nz": 2, "origin": [1, 0, 1], "cell_x": 1, "cell_z": 1,
+            "material": "NoMaterial"}}], "cameras": []}"#;
+        assert!(Scene::parse_json(heightfield).is_err());
+        assert!(Scene::parse_json(&heightfield.replace("[0, 0, 0]", "[0, 0, 0, 0]")).is_ok());
+    }
+}
@@ -0,0 +1,9 @@
+mod animation;
+mod gltf;
+mod json;
+mod pbrt;
+#[allow(clippy::module_inception)]
+mod scene;
+
+pub use animation::{AnimatedObject, Animation, Frame, MaterialKey, TransformKey};
+pub use scene::Scene;
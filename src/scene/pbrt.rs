@@ -0,0 +1,777 @@
+use super::Scene;
+use crate::camera::Camera;
+use crate::euclidean::{degrees_to_rads, Color, Point3, Transform, Vec3};
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::object::{Disk, Sphere, TriangleMesh};
+use crate::texture::{Image, Texture};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// pbrt's default film is 640x480
+const DEFAULT_ASPECT_RATIO: f64 = 640.0 / 480.0;
+
+// each level of subdivision quadruples the faces
+const MAX_SUBDIVISION_LEVELS: usize = 8;
+
+impl Scene {
+    pub fn load_pbrt<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse_pbrt(&fs::read_to_string(path)?, base)
+    }
+
+    // Reads the subset of pbrt-v3's scene format needed to compare simple
+    // scenes against it: transforms, attribute blocks, the perspective camera
+    // and film size, matte, plastic, glass, mirror and metal materials
+    // (named or not), diffuse area lights, and sphere, disk, trianglemesh,
+    // loopsubdiv and plymesh shapes. Other directives are skipped. Files
+    // named by Include and plymesh are resolved against the base directory.
+    //
+    // Subdivision surfaces are refined with Catmull–Clark rather than Loop's
+    // scheme, and meshes of any kind take the same "levels" parameter. They
+    // can also be displaced by constant or image map float textures, with
+    // pbrt-v4's "displacement" and "edgelength" parameters.
+    //
+    // pbrt's coordinate system is left handed, so the scene is mirrored in x
+    // to come out the same way round in ours.
+    pub fn parse_pbrt(text: &str, base: &Path) -> io::Result<Self> {
+        let mut builder = Builder {
+            base: base.to_path_buf(),
+            scene: Scene::default(),
+            state: State::default(),
+            stack: Vec::new(),
+            named_materials: HashMap::new(),
+            named_textures: HashMap::new(),
+            includes: Vec::new(),
+            camera: None,
+            aspect_ratio: DEFAULT_ASPECT_RATIO,
+        };
+        builder.parse(text)?;
+        builder.finish()
+    }
+}
+
+// the part of the graphics state saved by AttributeBegin
+#[derive(Clone)]
+struct State {
+    transform: Transform,
+    material: Material,
+    area_light: Option<Color>,
+    reverse_orientation: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            transform: Transform::identity(),
+            material: matte(&Params::default()),
+            area_light: None,
+            reverse_orientation: false,
+        }
+    }
+}
+
+struct Builder {
+    base: PathBuf,
+    scene: Scene,
+    state: State,
+    stack: Vec<State>,
+    named_materials: HashMap<String, Material>,
+    // float textures with the scale they are multiplied by
+    named_textures: HashMap<String, (Texture, f64)>,
+    // files being included, innermost last
+    includes: Vec<PathBuf>,
+    // camera parameters and the transform they were given with, kept until
+    // the film size is known
+    camera: Option<(Params, Transform)>,
+    aspect_ratio: f64,
+}
+
+impl Builder {
+    fn parse(&mut self, text: &str) -> io::Result<()> {
+        let tokens = tokenize(text)?;
+        let mut tokens = tokens.iter().peekable();
+        while let Some(token) = tokens.next() {
+            let name = match token {
+                Token::Word(name) => name.as_str(),
+                _ => return Err(invalid("expected a directive")),
+            };
+            let mut numbers = Vec::new();
+            // quoted words before the parameter list, usually just the type
+            let mut words = Vec::new();
+            let mut params = Params::default();
+            while let Some(token) = tokens.peek() {
+                match token {
+                    Token::Word(_) => break,
+                    Token::Number(n) => numbers.push(*n),
+                    Token::Open | Token::Close => (),
+                    Token::Str(s) => match s.split_once(char::is_whitespace) {
+                        Some((ty, param)) => {
+                            tokens.next();
+                            let values = params_values(&mut tokens)?;
+                            params
+                                .0
+                                .insert(param.trim().to_string(), (ty.to_string(), values));
+                            continue;
+                        }
+                        None => words.push(s.clone()),
+                    },
+                }
+                tokens.next();
+            }
+            self.directive(name, &numbers, &words, params)?;
+        }
+        Ok(())
+    }
+
+    fn directive(
+        &mut self,
+        name: &str,
+        numbers: &[f64],
+        words: &[String],
+        params: Params,
+    ) -> io::Result<()> {
+        let kind = words.first().map(String::as_str);
+        let expect = |count: usize| {
+            if numbers.len() == count {
+                Ok(())
+            } else {
+                Err(invalid(&format!("{} takes {} numbers", name, count)))
+            }
+        };
+        let kind_or_error = || kind.ok_or_else(|| invalid(&format!("{} needs a type", name)));
+        let n = numbers;
+        match name {
+            "Identity" => self.state.transform = Transform::identity(),
+            "Translate" => {
+                expect(3)?;
+                self.concat(Transform::translation(&Vec3::new(n[0], n[1], n[2])));
+            }
+            "Scale" => {
+                expect(3)?;
+                self.concat(Transform::scaling(&Vec3::new(n[0], n[1], n[2])));
+            }
+            "Rotate" => {
+                expect(4)?;
+                // the same matrix as pbrt's, the mirroring afterwards takes
+                // care of the handedness
+                let axis = Vec3::new(n[1], n[2], n[3]);
+                self.concat(Transform::rotation_about(&axis, degrees_to_rads(n[0])));
+            }
+            "LookAt" => {
+                expect(9)?;
+                let eye = Point3::new(n[0], n[1], n[2]);
+                let look = Point3::new(n[3], n[4], n[5]);
+                let up = Vec3::new(n[6], n[7], n[8]);
+                self.concat(look_at(&eye, &look, &up)?);
+            }
+            "Transform" | "ConcatTransform" => {
+                expect(16)?;
+                let mut columns = [0.0; 16];
+                columns.copy_from_slice(n);
+                if name == "Transform" {
+                    self.state.transform = Transform::identity();
+                }
+                self.concat(Transform::from_columns(&columns));
+            }
+            "ReverseOrientation" => {
+                self.state.reverse_orientation = !self.state.reverse_orientation
+            }
+            "AttributeBegin" | "TransformBegin" => self.stack.push(self.state.clone()),
+            "AttributeEnd" | "TransformEnd" => {
+                let saved = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| invalid(&format!("unmatched {}", name)))?;
+                if name == "AttributeEnd" {
+                    self.state = saved;
+                } else {
+                    self.state.transform = saved.transform;
+                }
+            }
+            "Camera" => {
+                if kind_or_error()? != "perspective" {
+                    return Err(invalid("only perspective cameras are supported"));
+                }
+                self.camera = Some((params, self.state.transform));
+            }
+            "Film" => {
+                let width = params.float("xresolution", 640.0);
+                let height = params.float("yresolution", 480.0);
+                self.aspect_ratio = width / height;
+            }
+            "WorldBegin" => {
+                self.state.transform = Transform::identity();
+            }
+            "Material" => self.state.material = material(kind_or_error()?, &params)?,
+            "MakeNamedMaterial" => {
+                let material_type = params
+                    .string("type")
+                    .ok_or_else(|| invalid("named material without a type"))?;
+                let material = material(material_type, &params)?;
+                self.named_materials
+                    .insert(kind_or_error()?.to_string(), material);
+            }
+            "NamedMaterial" => {
+                let name = kind_or_error()?;
+                self.state.material = self
+                    .named_materials
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| invalid(&format!("unknown material {}", name)))?;
+            }
+            "Texture" => {
+                if let [name, _, class] = words {
+                    if let Some(texture) = self.texture(class, &params)? {
+                        self.named_textures.insert(name.clone(), texture);
+                    }
+                } else {
+                    return Err(invalid("Texture needs a name, type and class"));
+                }
+            }
+            "AreaLightSource" => {
+                self.state.area_light = Some(params.rgb("L", Color::new(1.0, 1.0, 1.0)));
+            }
+            "Shape" => self.shape(kind_or_error()?, &params)?,
+            "Include" => {
+                let path = fs::canonicalize(self.base.join(kind_or_error()?))?;
+                if self.includes.contains(&path) {
+                    return Err(invalid("include cycle"));
+                }
+                let text = fs::read_to_string(&path)?;
+                self.includes.push(path);
+                self.parse(&text)?;
+                self.includes.pop();
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn concat(&mut self, transform: Transform) {
+        self.state.transform = self.state.transform * transform;
+    }
+
+    // from pbrt's world space to ours
+    fn to_world(&self) -> Transform {
+        Transform::scaling(&Vec3::new(-1.0, 1.0, 1.0)) * self.state.transform
+    }
+
+    fn shape(&mut self, kind: &str, params: &Params) -> io::Result<()> {
+        let material = match self.state.area_light {
+            Some(emit) => Material::DiffuseLight { emit },
+            None => self.state.material.clone(),
+        };
+        let transform = self.to_world();
+        match kind {
+            "sphere" => {
+                let radius = params.float("radius", 1.0);
+                // only uniform scales keep spheres round
+                let scale = transform.vector(&Vec3::new(1.0, 0.0, 0.0)).len();
+                self.scene.world.add(Arc::new(Sphere::new(
+                    transform.point(&Point3::default()),
+                    radius * scale,
+                    material,
+                )));
+            }
+            "disk" => {
+                let height = params.float("height", 0.0);
+                let radius = params.float("radius", 1.0);
+                let scale = transform.vector(&Vec3::new(1.0, 0.0, 0.0)).len();
+                let mut normal = transform.vector(&Vec3::new(0.0, 0.0, 1.0));
+                if self.state.reverse_orientation {
+                    normal = -normal;
+                }
+                self.scene.world.add(Arc::new(Disk::new(
+                    transform.point(&Point3::new(0.0, 0.0, height)),
+                    normal,
+                    radius * scale,
+                    material,
+                )));
+            }
+            "trianglemesh" | "loopsubdiv" => {
+                let indices = params
+                    .numbers("indices")
+                    .ok_or_else(|| invalid(&format!("{} without indices", kind)))?;
+                let points = params
+                    .numbers("P")
+                    .ok_or_else(|| invalid(&format!("{} without points", kind)))?;
+                let vertices: Vec<Point3> = points
+                    .chunks_exact(3)
+                    .map(|p| Point3::new(p[0], p[1], p[2]))
+                    .collect();
+                if indices.iter().any(|i| *i as usize >= vertices.len()) {
+                    return Err(invalid(&format!("{} index out of range", kind)));
+                }
+                let faces = indices
+                    .chunks_exact(3)
+                    .map(|f| f.iter().map(|i| *i as usize).collect())
+                    .collect();
+                let mut mesh = Mesh::new(vertices, faces);
+                if let Some(uvs) = params.numbers("uv").or_else(|| params.numbers("st")) {
+                    let uvs: Vec<(f64, f64)> =
+                        uvs.chunks_exact(2).map(|uv| (uv[0], uv[1])).collect();
+                    if uvs.len() == mesh.vertices.len() {
+                        mesh = mesh.with_uvs(uvs);
+                    }
+                }
+                let levels = if kind == "loopsubdiv" { 3.0 } else { 0.0 };
+                let mesh = self.refine(mesh, params, levels)?;
+                self.add_mesh(mesh, &transform, material);
+            }
+            "plymesh" => {
+                let filename = params
+                    .string("filename")
+                    .ok_or_else(|| invalid("plymesh without a filename"))?;
+                let mesh = self.refine(Mesh::load_ply(self.base.join(filename))?, params, 0.0)?;
+                self.add_mesh(mesh, &transform, material);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // constant and image map textures; others are skipped
+    fn texture(&self, class: &str, params: &Params) -> io::Result<Option<(Texture, f64)>> {
+        let scale = params.float("scale", 1.0);
+        Ok(match class {
+            "constant" => {
+                let value = params.float("value", 1.0);
+                Some((Texture::Solid(Color::new(value, value, value)), scale))
+            }
+            "imagemap" => {
+                let filename = params
+                    .string("filename")
+                    .ok_or_else(|| invalid("imagemap without a filename"))?;
+                let mut image = Image::load(self.base.join(filename))?;
+                if params.string("encoding") != Some("linear") {
+                    image = image.srgb_to_linear();
+                }
+                Some((Texture::Image(Arc::new(image)), scale))
+            }
+            _ => None,
+        })
+    }
+
+    // Subdivides a mesh by its "levels" parameter, then displaces it by the
+    // float texture named by "displacement" after splitting its triangles
+    // until their edges are no longer than "edgelength", as pbrt-v4 does.
+    fn refine(&self, mesh: Mesh, params: &Params, default_levels: f64) -> io::Result<Mesh> {
+        let levels = params.float("levels", default_levels);
+        if !(0.0..=MAX_SUBDIVISION_LEVELS as f64).contains(&levels) {
+            return Err(invalid("subdivision levels out of range"));
+        }
+        let mesh = mesh.subdivide(levels as usize);
+        let name = match params.string("displacement") {
+            Some(name) => name,
+            None => return Ok(mesh),
+        };
+        let (height, scale) = self
+            .named_textures
+            .get(name)
+            .ok_or_else(|| invalid(&format!("unknown texture {}", name)))?;
+        let edge_length = params.float("edgelength", 1.0);
+        if !(edge_length > 0.0 && edge_length.is_finite()) {
+            return Err(invalid("edge length must be positive"));
+        }
+        let longest = mesh
+            .triangles()
+            .iter()
+            .flat_map(|t| (0..3).map(move |i| (t[i], t[(i + 1) % 3])))
+            .map(|(a, b)| (mesh.vertices[a] - mesh.vertices[b]).len())
+            .fold(0.0, f64::max);
+        // each split halves the edges; very fine dicing is capped like
+        // subdivision is
+        let splits = (longest / edge_length).log2().ceil().max(0.0);
+        let splits = (splits as usize).min(MAX_SUBDIVISION_LEVELS);
+        Ok(mesh.tessellate(splits).displace(height, *scale))
+    }
+
+    fn add_mesh(&mut self, mut mesh: Mesh, transform: &Transform, material: Material) {
+        for p in mesh.vertices.iter_mut() {
+            *p = transform.point(p);
+        }
+        // the mirroring into our space turns faces inside out, which reverse
+        // orientation would do as well
+        if !self.state.reverse_orientation {
+            for face in mesh.faces.iter_mut() {
+                face.reverse();
+            }
+        }
+        self.scene
+            .world
+            .add(Arc::new(TriangleMesh::new(&mesh, material)));
+    }
+
+    fn finish(mut self) -> io::Result<Scene> {
+        if let Some((params, world_to_camera)) = self.camera.take() {
+            let camera_to_world = world_to_camera
+                .inverse()
+                .ok_or_else(|| invalid("camera transform can't be inverted"))?;
+            let mirror = Transform::scaling(&Vec3::new(-1.0, 1.0, 1.0));
+            let to_world = mirror * camera_to_world;
+            let look_from = to_world.point(&Point3::default());
+            let forward = to_world.vector(&Vec3::new(0.0, 0.0, 1.0)).unit_vector();
+            let up = to_world.vector(&Vec3::new(0.0, 1.0, 0.0));
+
+            // the field of view is along the shorter side of the image
+            let fov = params.float("fov", 90.0);
+            let vertical_fov = if self.aspect_ratio >= 1.0 {
+                fov
+            } else {
+                let half = degrees_to_rads(fov) / 2.0;
+                2.0 * (half.tan() / self.aspect_ratio).atan().to_degrees()
+            };
+            let lens_radius = params.float("lensradius", 0.0);
+            let focus_dist = if lens_radius > 0.0 {
+                params.float("focaldistance", 1e6)
+            } else {
+                1.0
+            };
+            self.scene.cameras.push(Camera::new(
+                &look_from,
+                &(look_from + forward),
+                &up,
+                vertical_fov,
+                self.aspect_ratio,
+                2.0 * lens_radius,
+                focus_dist,
+            ));
+        }
+        Ok(self.scene)
+    }
+}
+
+// pbrt's LookAt, which takes world space to camera space
+fn look_at(eye: &Point3, look: &Point3, up: &Vec3) -> io::Result<Transform> {
+    let dir = (*look - *eye).unit_vector();
+    let right = up.unit_vector().cross(&dir);
+    if right.near_zero() {
+        return Err(invalid(
+            "LookAt up vector is parallel to the view direction",
+        ));
+    }
+    let right = right.unit_vector();
+    let new_up = dir.cross(&right);
+    let camera_to_world = Transform::from_columns(&[
+        right.x(),
+        right.y(),
+        right.z(),
+        0.0,
+        new_up.x(),
+        new_up.y(),
+        new_up.z(),
+        0.0,
+        dir.x(),
+        dir.y(),
+        dir.z(),
+        0.0,
+        eye.x(),
+        eye.y(),
+        eye.z(),
+        1.0,
+    ]);
+    camera_to_world
+        .inverse()
+        .ok_or_else(|| invalid("degenerate LookAt"))
+}
+
+fn matte(params: &Params) -> Material {
+    Material::Lambertian {
+        albedo: params.rgb("Kd", Color::new(0.5, 0.5, 0.5)),
+    }
+}
+
+fn material(kind: &str, params: &Params) -> io::Result<Material> {
+    Ok(match kind {
+        "matte" => matte(params),
+        "plastic" => Material::Coated {
+            base: Arc::new(Material::Lambertian {
+                albedo: params.rgb("Kd", Color::new(0.25, 0.25, 0.25)),
+            }),
+            ir: 1.5,
+        },
+        "glass" => Material::Dialectric {
+            ir: params.float("eta", params.float("index", 1.5)),
+        },
+        "mirror" => Material::Metal {
+            albedo: params.rgb("Kr", Color::new(0.9, 0.9, 0.9)),
+            fuzz_in: 0.0,
+        },
+        "metal" => {
+            // reflectance at normal incidence from the complex index of
+            // refraction, defaulting to copper like pbrt
+            let eta = params.rgb("eta", Color::new(0.200_438, 0.924_033, 1.102_21));
+            let k = params.rgb("k", Color::new(3.912_95, 2.452_85, 2.142_19));
+            let reflectance =
+                |n: f64, k: f64| ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k);
+            Material::Metal {
+                albedo: Color::new(
+                    reflectance(eta.x(), k.x()),
+                    reflectance(eta.y(), k.y()),
+                    reflectance(eta.z(), k.z()),
+                ),
+                fuzz_in: params.float("roughness", 0.01),
+            }
+        }
+        // "none" is used for interfaces between media, which we don't have
+        "none" | "" => Material::NoMaterial,
+        _ => return Err(invalid(&format!("unsupported material {}", kind))),
+    })
+}
+
+// parameter lists by name, with their declared type and values
+#[derive(Default)]
+struct Params(HashMap<String, (String, Vec<Token>)>);
+
+impl Params {
+    fn numbers(&self, name: &str) -> Option<Vec<f64>> {
+        let (_, values) = self.0.get(name)?;
+        values
+            .iter()
+            .map(|v| match v {
+                Token::Number(n) => Some(*n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn float(&self, name: &str, default: f64) -> f64 {
+        self.numbers(name)
+            .and_then(|n| n.first().copied())
+            .unwrap_or(default)
+    }
+
+    // rgb and color parameters; spectra are not supported
+    fn rgb(&self, name: &str, default: Color) -> Color {
+        match self.0.get(name) {
+            Some((ty, _)) if ty == "rgb" || ty == "color" => match self.numbers(name) {
+                Some(c) if c.len() == 3 => Color::new(c[0], c[1], c[2]),
+                _ => default,
+            },
+            _ => default,
+        }
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        match self.0.get(name)?.1.first()? {
+            Token::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(f64),
+    Open,
+    Close,
+}
+
+// the values of a parameter, either bracketed or a single one
+fn params_values<'a, I: Iterator<Item = &'a Token>>(
+    tokens: &mut std::iter::Peekable<I>,
+) -> io::Result<Vec<Token>> {
+    match tokens.next() {
+        Some(Token::Open) => {
+            let mut values = Vec::new();
+            loop {
+                match tokens.next() {
+                    Some(Token::Close) => return Ok(values),
+                    Some(t @ (Token::Number(_) | Token::Str(_))) => values.push(t.clone()),
+                    // booleans may be written bare
+                    Some(Token::Word(w)) if w == "true" || w == "false" => {
+                        values.push(Token::Str(w.clone()))
+                    }
+                    _ => return Err(invalid("unterminated parameter list")),
+                }
+            }
+        }
+        Some(t @ (Token::Number(_) | Token::Str(_))) => Ok(vec![t.clone()]),
+        _ => Err(invalid("parameter without a value")),
+    }
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '[' => tokens.push(Token::Open),
+            ']' => tokens.push(Token::Close),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => s.push(c),
+                        None => return Err(invalid("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_whitespace() => (),
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"[]\"#".contains(*c))
+                {
+                    end = i + c.len_utf8();
+                }
+                let word = &text[start..end];
+                tokens.push(match word.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => Token::Word(word.to_string()),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::Ray;
+    use crate::hittable::HitRecord;
+
+    const SCENE: &str = r#"
+        # a light above a red quad, seen from the front
+        LookAt 0 1 -5  0 1 0  0 1 0
+        Camera "perspective" "float fov" [40]
+        Film "image" "integer xresolution" [300] "integer yresolution" [200]
+            "string filename" "out.exr"
+        Sampler "halton" "integer pixelsamples" 16
+        WorldBegin
+        MakeNamedMaterial "red" "string type" "matte" "rgb Kd" [0.6 0.1 0.1]
+        AttributeBegin
+            NamedMaterial "red"
+            Translate 0 0 1
+            Shape "trianglemesh" "integer indices" [0 2 1 0 3 2]
+                "point P" [-1 0 0  1 0 0  1 2 0  -1 2 0]
+        AttributeEnd
+        AttributeBegin
+            AreaLightSource "diffuse" "rgb L" [8 8 8]
+            Translate 2 3 0
+            Shape "sphere" "float radius" 0.5
+        AttributeEnd
+        Material "glass"
+        Shape "disk" "float radius" [3] "float height" 3
+        WorldEnd
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let scene = Scene::parse_pbrt(SCENE, Path::new("")).unwrap();
+        assert_eq!(scene.cameras.len(), 1);
+        let ray = scene.cameras[0].get_ray(0.5, 0.5);
+        assert!((ray.origin - Point3::new(0.0, 1.0, -5.0)).len() < 1e-9);
+
+        let mut record = HitRecord::default();
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!((record.p.z() - 1.0).abs() < 1e-9);
+        assert!(record.front_face);
+        assert!(matches!(
+            *record.material,
+            Material::Lambertian { albedo } if albedo == Color::new(0.6, 0.1, 0.1)
+        ));
+    }
+
+    #[test]
+    fn test_mirrored_into_right_handed_space() {
+        let scene = Scene::parse_pbrt(SCENE, Path::new("")).unwrap();
+        // pbrt's +x is to the right of a camera looking down +z, and the
+        // light is there, so it has to be on the right of our image too
+        let ray = scene.cameras[0].get_ray(0.5, 0.5);
+        let to_light = Ray::new(ray.origin, Point3::new(-2.0, 3.0, 0.0) - ray.origin);
+        let mut record = HitRecord::default();
+        assert!(scene
+            .world
+            .hit(&to_light, 0.001, f64::INFINITY, &mut record));
+        assert!(matches!(*record.material, Material::DiffuseLight { .. }));
+
+        let right = scene.cameras[0].get_ray(1.0, 0.5);
+        assert!(right.dir.x() < 0.0);
+    }
+
+    #[test]
+    fn test_loopsubdiv_smooths() {
+        // a tetrahedron with its apex on the y axis
+        let shape = |kind: &str| {
+            format!(
+                "Shape \"{}\" \"integer indices\" [0 1 2  0 2 3  0 3 1  1 3 2]
+                    \"point P\" [0 2 0  -1 0 -1  1 0 -1  0 0 1]",
+                kind
+            )
+        };
+        let apex = |text: &str| {
+            let scene = Scene::parse_pbrt(text, Path::new("")).unwrap();
+            let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+            let mut record = HitRecord::default();
+            assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record));
+            record.p.y()
+        };
+        assert!((apex(&shape("trianglemesh")) - 2.0).abs() < 1e-9);
+        assert!(apex(&shape("loopsubdiv")) < 1.5);
+        let flat = format!("{} \"integer levels\" 0", shape("loopsubdiv"));
+        assert!((apex(&flat) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_displacement() {
+        // a quad in the xz plane, wound to face down in pbrt's space
+        let text = r#"
+            Texture "bump" "float" "constant" "float value" 0.5 "float scale" 2
+            Shape "trianglemesh" "integer indices" [0 1 2  0 2 3]
+                "point P" [-1 0 -1  1 0 -1  1 0 1  -1 0 1]
+                "texture displacement" "bump" "float edgelength" 0.5
+        "#;
+        let scene = Scene::parse_pbrt(text, Path::new("")).unwrap();
+        let ray = Ray::new(Point3::new(0.3, 5.0, 0.2), Vec3::new(0.0, -1.0, 0.0));
+        let mut record = HitRecord::default();
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut record));
+        assert!((record.p.y() + 1.0).abs() < 1e-9);
+
+        let missing = text.replacen("\"bump\" \"float", "\"other\" \"float", 1);
+        assert!(Scene::parse_pbrt(&missing, Path::new("")).is_err());
+        let zero = text.replace("edgelength\" 0.5", "edgelength\" 0");
+        assert!(Scene::parse_pbrt(&zero, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = std::env::temp_dir().join(format!("pbrt_include_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.pbrt"), "Include \"b.pbrt\"").unwrap();
+        fs::write(dir.join("b.pbrt"), "Include \"a.pbrt\"").unwrap();
+        fs::write(dir.join("c.pbrt"), "Shape \"sphere\"").unwrap();
+        let error = Scene::parse_pbrt("Include \"a.pbrt\"", &dir).err().unwrap();
+        // the same file may still be included more than once in a row
+        let twice = Scene::parse_pbrt("Include \"c.pbrt\" Include \"c.pbrt\"", &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(error.to_string(), "include cycle");
+        assert!(twice.is_ok());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Scene::parse_pbrt("Translate 1 2", Path::new("")).is_err());
+        assert!(Scene::parse_pbrt("AttributeEnd", Path::new("")).is_err());
+        assert!(Scene::parse_pbrt("NamedMaterial \"missing\"", Path::new("")).is_err());
+        assert!(Scene::parse_pbrt("Shape \"sphere", Path::new("")).is_err());
+        let deep = "Shape \"loopsubdiv\" \"integer levels\" 20 \"integer indices\" [0 1 2]
+            \"point P\" [0 0 0  1 0 0  0 1 0]";
+        assert!(Scene::parse_pbrt(deep, Path::new("")).is_err());
+    }
+}
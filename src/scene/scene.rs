@@ -0,0 +1,52 @@
+use super::Animation;
+use crate::camera::Camera;
+use crate::hittable::HittableList;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+// Everything loaded from a scene file: the objects to render and any cameras
+// placed among them, in the order they were found, with anything keyframed
+// to change over time.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Scene {
+    pub world: HittableList,
+    pub cameras: Vec<Camera>,
+    #[serde(default)]
+    pub animation: Animation,
+}
+
+impl Scene {
+    // Loads a scene in any of the supported formats, told apart by the file
+    // extension: .json, .gltf or .glb, or .pbrt.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Self::load_json(path),
+            Some("gltf") | Some("glb") => Self::load_gltf(path),
+            Some("pbrt") => Self::load_pbrt(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown scene file extension",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_unknown_extension() {
+        let error = Scene::load("scene.obj").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        // the format is picked before the file is read
+        let error = Scene::load("missing.json").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
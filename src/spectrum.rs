@@ -0,0 +1,143 @@
+use crate::euclidean::{random_in_range, Color, Vec3};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+// visible range sampled in spectral mode, in nanometers
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 780.0;
+
+// Fraunhofer d line, used when a ray carries no wavelength
+pub const LAMBDA_D: f64 = 587.56;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Dispersion {
+    // n = a + b / λ², with λ in micrometers
+    Cauchy { a: f64, b: f64 },
+    // n² = 1 + Σ bᵢλ² / (λ² - cᵢ), with λ in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub const BK7: Self = Self::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+
+    pub const FUSED_SILICA: Self = Self::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148_3, 0.013_512_063, 97.934_003],
+    };
+
+    pub const DIAMOND: Self = Self::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.106 * 0.106, 0.175 * 0.175, 0.0],
+    };
+
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l = wavelength / 1000.0;
+        let l2 = l * l;
+        match self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+#[inline(always)]
+pub fn sample_wavelength() -> f64 {
+    random_in_range(LAMBDA_MIN, LAMBDA_MAX)
+}
+
+#[inline(always)]
+fn lobe(x: f64, mu: f64, sigma_lo: f64, sigma_hi: f64) -> f64 {
+    let t = (x - mu) / if x < mu { sigma_lo } else { sigma_hi };
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 2° color matching functions, using the multi-lobe fit from
+// Wyman, Sloan and Shirley, "Simple Analytic Approximations to the CIE XYZ
+// Color Matching Functions" (JCGT 2013)
+pub fn cie_xyz(wavelength: f64) -> Vec3 {
+    let l = wavelength;
+    Vec3::new(
+        1.056 * lobe(l, 599.8, 37.9, 31.0) + 0.362 * lobe(l, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(l, 501.1, 20.4, 26.2),
+        0.821 * lobe(l, 568.8, 46.9, 40.5) + 0.286 * lobe(l, 530.9, 16.3, 31.1),
+        1.217 * lobe(l, 437.0, 11.8, 36.0) + 0.681 * lobe(l, 459.0, 26.0, 13.8),
+    )
+}
+
+pub fn xyz_to_linear_srgb(xyz: &Vec3) -> Color {
+    Color::new(
+        3.2406 * xyz.x() - 1.5372 * xyz.y() - 0.4986 * xyz.z(),
+        -0.9689 * xyz.x() + 1.8758 * xyz.y() + 0.0415 * xyz.z(),
+        0.0557 * xyz.x() - 0.2040 * xyz.y() + 1.0570 * xyz.z(),
+    )
+}
+
+#[inline(always)]
+fn unnormalized_rgb(wavelength: f64) -> Color {
+    let rgb = xyz_to_linear_srgb(&cie_xyz(wavelength));
+    // out of gamut spectral colors are clipped rather than letting a single
+    // sample push a pixel negative
+    Color::new(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0))
+}
+
+// Returns the RGB contribution of a single wavelength sample. The weights are
+// normalized so that averaging them over uniformly sampled wavelengths gives
+// white, i.e. an RGB path that is not wavelength dependent renders the same
+// as it would in RGB mode.
+pub fn wavelength_to_rgb(wavelength: f64) -> Color {
+    static NORMALIZATION: OnceLock<Color> = OnceLock::new();
+    let norm = NORMALIZATION.get_or_init(|| {
+        let steps = 4000;
+        let dl = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let sum = (0..steps)
+            .map(|i| unnormalized_rgb(LAMBDA_MIN + (i as f64 + 0.5) * dl))
+            .fold(Color::default(), |acc, c| acc + c);
+        sum / steps as f64
+    });
+    let rgb = unnormalized_rgb(wavelength);
+    Color::new(rgb.x() / norm.x(), rgb.y() / norm.y(), rgb.z() / norm.z())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bk7_ior() {
+        assert!((Dispersion::BK7.ior(LAMBDA_D) - 1.5168).abs() < 1e-4);
+        assert!(Dispersion::BK7.ior(450.0) > Dispersion::BK7.ior(650.0));
+    }
+
+    #[test]
+    fn test_cauchy_ior() {
+        let d = Dispersion::Cauchy { a: 1.5, b: 0.004 };
+        assert!((d.ior(1000.0) - 1.504).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wavelength_to_rgb_averages_to_white() {
+        let steps = 1000;
+        let dl = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let avg = (0..steps)
+            .map(|i| wavelength_to_rgb(LAMBDA_MIN + (i as f64 + 0.5) * dl))
+            .fold(Color::default(), |acc, c| acc + c)
+            / steps as f64;
+        for i in 0..3 {
+            assert!((avg[i] - 1.0).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_wavelength_to_rgb_hues() {
+        let red = wavelength_to_rgb(650.0);
+        assert!(red.x() > red.y() && red.x() > red.z());
+        let blue = wavelength_to_rgb(450.0);
+        assert!(blue.z() > blue.x() && blue.z() > blue.y());
+    }
+}
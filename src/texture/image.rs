@@ -0,0 +1,362 @@
+use crate::euclidean::Color;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+use zune_jpeg::JpegDecoder;
+
+// An image with channels stored as-is in [0, 1]; no gamma decoding is done
+// so the data can hold normals and heights as well as colors.
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "SavedImage")]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+// an image as written to scene files, checked before it's used
+#[derive(Deserialize)]
+struct SavedImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl TryFrom<SavedImage> for Image {
+    type Error = String;
+
+    fn try_from(saved: SavedImage) -> Result<Self, Self::Error> {
+        if saved.width == 0 || saved.height == 0 {
+            return Err("image has no pixels".to_string());
+        }
+        if saved.width.checked_mul(saved.height) != Some(saved.pixels.len()) {
+            return Err("image pixels don't match its size".to_string());
+        }
+        Ok(Self::new(saved.width, saved.height, saved.pixels))
+    }
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert!(width > 0 && height > 0, "images need at least one pixel");
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Loads a PNG, JPEG or netpbm image, telling them apart by their contents.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.starts_with(b"\x89PNG") {
+            Self::parse_png(bytes)
+        } else if bytes.starts_with(&[0xff, 0xd8]) {
+            Self::parse_jpeg(bytes)
+        } else {
+            Self::parse_pnm(bytes)
+        }
+    }
+
+    pub fn parse_png(bytes: &[u8]) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(io::Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| invalid(&e.to_string()))?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or_else(|| invalid("png too large"))?;
+        let mut buffer = vec![0; size];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| invalid(&e.to_string()))?;
+        let channels = info.color_type.samples();
+        Self::from_bytes(
+            info.width as usize,
+            info.height as usize,
+            channels,
+            &buffer[..info.buffer_size()],
+        )
+    }
+
+    pub fn parse_jpeg(bytes: &[u8]) -> io::Result<Self> {
+        let mut decoder = JpegDecoder::new(io::Cursor::new(bytes));
+        let buffer = decoder.decode().map_err(|e| invalid(&format!("{:?}", e)))?;
+        let info = decoder
+            .info()
+            .ok_or_else(|| invalid("missing jpeg header"))?;
+        let channels = decoder
+            .output_colorspace()
+            .map_or(3, |colorspace| colorspace.num_components());
+        Self::from_bytes(info.width as usize, info.height as usize, channels, &buffer)
+    }
+
+    // 8 bit samples in rows from the top, the first three channels of each
+    // pixel being used as its color or one channel as its gray level
+    fn from_bytes(width: usize, height: usize, channels: usize, bytes: &[u8]) -> io::Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(invalid("image has no pixels"));
+        }
+        let size = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| invalid("image too large"))?;
+        if bytes.len() < size {
+            return Err(invalid("truncated image data"));
+        }
+        let pixels = bytes
+            .chunks(channels)
+            .take(width * height)
+            .map(|c| {
+                let c: Vec<f64> = c.iter().map(|b| *b as f64 / 255.0).collect();
+                if channels < 3 {
+                    Color::new(c[0], c[0], c[0])
+                } else {
+                    Color::new(c[0], c[1], c[2])
+                }
+            })
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+
+    // Loads a netpbm image, either graymap (P2, P5) or pixmap (P3, P6).
+    pub fn load_pnm<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse_pnm(&fs::read(path)?)
+    }
+
+    pub fn parse_pnm(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = PnmReader { bytes, pos: 0 };
+        let (channels, binary) = match reader.token()?.as_str() {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            _ => return Err(invalid("unsupported netpbm format")),
+        };
+        let width = reader.number()?;
+        let height = reader.number()?;
+        if width == 0 || height == 0 {
+            return Err(invalid("image has no pixels"));
+        }
+        let max_value = reader.number()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("invalid netpbm max value"));
+        }
+
+        // the sizes come from the file, so may be too large to count
+        let too_large = || invalid("image too large");
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(too_large)?;
+        let samples: Vec<usize> = if binary {
+            // a single whitespace byte separates the header from the raster
+            let start = reader.pos + 1;
+            let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+            let end = count
+                .checked_mul(bytes_per_sample)
+                .and_then(|n| n.checked_add(start))
+                .ok_or_else(too_large)?;
+            let raster = bytes
+                .get(start..end)
+                .ok_or_else(|| invalid("truncated netpbm raster"))?;
+            raster
+                .chunks(bytes_per_sample)
+                .map(|c| c.iter().fold(0, |acc, b| (acc << 8) | *b as usize))
+                .collect()
+        } else {
+            (0..count)
+                .map(|_| reader.number())
+                .collect::<io::Result<_>>()?
+        };
+
+        let scale = 1.0 / max_value as f64;
+        let pixels = samples
+            .chunks(channels)
+            .map(|c| {
+                if channels == 1 {
+                    let g = c[0] as f64 * scale;
+                    Color::new(g, g, g)
+                } else {
+                    Color::new(
+                        c[0] as f64 * scale,
+                        c[1] as f64 * scale,
+                        c[2] as f64 * scale,
+                    )
+                }
+            })
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+
+    // Converts colors stored with the sRGB transfer curve, as most color
+    // textures are, to the linear values used for rendering.
+    pub fn srgb_to_linear(&self) -> Self {
+        let decode = |c: f64| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|c| Color::new(decode(c.x()), decode(c.y()), decode(c.z())))
+            .collect();
+        Self::new(self.width, self.height, pixels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // pixel (0, 0) is the top left corner
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        self.pixels[j.min(self.height - 1) * self.width + i.min(self.width - 1)]
+    }
+
+    // nearest pixel lookup with the texture repeating outside of [0, 1]
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+        self.pixel(
+            (u * self.width as f64) as usize,
+            (v * self.height as f64) as usize,
+        )
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// Tokenizer for the ascii parts of a netpbm file. Fields are whitespace
+// separated and may be interleaved with comments running to the end of the
+// line.
+struct PnmReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl PnmReader<'_> {
+    fn token(&mut self) -> io::Result<String> {
+        let bytes = self.bytes;
+        loop {
+            while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            if self.pos < bytes.len() && bytes[self.pos] == b'#' {
+                while self.pos < bytes.len() && bytes[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let start = self.pos;
+        while self.pos < bytes.len() && !bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid("unexpected end of netpbm data"));
+        }
+        Ok(String::from_utf8_lossy(&bytes[start..self.pos]).into_owned())
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        self.token()?
+            .parse()
+            .map_err(|_| invalid("expected a number in netpbm data"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii_pixmap() {
+        let image = Image::parse_pnm(b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n").unwrap();
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.height(), 1);
+        assert_eq!(image.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(image.pixel(1, 0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_binary_graymap() {
+        let image = Image::parse_pnm(b"P5 2 2 255\n\x00\xff\xff\x00").unwrap();
+        assert_eq!(image.pixel(1, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(image.pixel(1, 1), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_sample_origin_is_bottom_left() {
+        let image = Image::parse_pnm(b"P2 1 2 1\n1\n0\n").unwrap();
+        assert_eq!(image.sample(0.0, 0.0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(image.sample(0.0, 0.75), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_truncated_raster() {
+        assert!(Image::parse_pnm(b"P6 2 2 255\n\x00").is_err());
+        // sizes whose raster can't even be counted
+        let error = Image::parse_pnm(b"P6 4294967296 4294967296 255\n\x00")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "image too large");
+        assert!(Image::parse_pnm(b"P3 4294967296 4294967296 255\n").is_err());
+    }
+
+    #[test]
+    fn test_no_pixels() {
+        assert!(Image::parse_pnm(b"P3 0 0 255\n").is_err());
+        assert!(Image::parse_pnm(b"P2 3 0 255\n").is_err());
+        assert!(
+            serde_json::from_str::<Image>(r#"{"width": 0, "height": 0, "pixels": []}"#).is_err()
+        );
+        assert!(
+            serde_json::from_str::<Image>(r#"{"width": 2, "height": 1, "pixels": []}"#).is_err()
+        );
+        assert!(serde_json::from_str::<Image>(
+            r#"{"width": 1, "height": 1, "pixels": [[1, 0, 0]]}"#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_parse_png() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128])
+                .unwrap();
+        }
+        let image = Image::parse(&bytes).unwrap();
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(image.pixel(1, 0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_srgb_to_linear() {
+        let image = Image::new(1, 1, vec![Color::new(0.0, 0.5, 1.0)]).srgb_to_linear();
+        let c = image.pixel(0, 0);
+        assert_eq!(c.x(), 0.0);
+        assert!((c.y() - 0.214).abs() < 1e-3);
+        assert!((c.z() - 1.0).abs() < 1e-12);
+    }
+}
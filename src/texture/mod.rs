@@ -0,0 +1,10 @@
+mod image;
+mod perlin;
+mod surface_map;
+#[allow(clippy::module_inception)]
+mod texture;
+
+pub use self::image::Image;
+pub use perlin::{perlin, Perlin};
+pub use surface_map::SurfaceMap;
+pub use texture::Texture;
@@ -0,0 +1,106 @@
+use crate::euclidean::{Point3, Vec3};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::sync::OnceLock;
+
+const POINT_COUNT: usize = 256;
+
+pub struct Perlin {
+    random_vectors: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+// Shared noise instance, seeded so procedural textures are identical between
+// runs.
+pub fn perlin() -> &'static Perlin {
+    static PERLIN: OnceLock<Perlin> = OnceLock::new();
+    PERLIN.get_or_init(|| Perlin::new(0))
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let random_vectors = (0..POINT_COUNT)
+            .map(|_| {
+                Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                )
+                .unit_vector()
+            })
+            .collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm_x = permutation();
+        let perm_y = permutation();
+        let perm_z = permutation();
+        Self {
+            random_vectors,
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    // gradient noise in roughly [-1, 1]
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mask = POINT_COUNT as i64 - 1;
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.random_vectors[self.perm_x[((i + di as i64) & mask) as usize]
+                        ^ self.perm_y[((j + dj as i64) & mask) as usize]
+                        ^ self.perm_z[((k + dk as i64) & mask) as usize]];
+                }
+            }
+        }
+
+        // hermite smoothing of the interpolation weights
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+        let mut accum = 0.0;
+        for (di, plane) in c.iter().enumerate() {
+            for (dj, row) in plane.iter().enumerate() {
+                for (dk, corner) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * corner.dot(&weight);
+                }
+            }
+        }
+        accum
+    }
+
+    // fractal sum of octaves of noise, each at twice the frequency and half
+    // the amplitude of the last
+    pub fn fbm(&self, p: &Point3, octaves: usize) -> f64 {
+        let mut sum = 0.0;
+        let mut point = *p;
+        let mut amplitude = 0.5;
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(&point);
+            point *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum
+    }
+}
@@ -0,0 +1,84 @@
+use super::Texture;
+use crate::euclidean::Vec3;
+use crate::hittable::HitRecord;
+use serde::{Deserialize, Serialize};
+
+// world space step used to take finite differences of bump maps
+const BUMP_EPSILON: f64 = 1e-3;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SurfaceMap {
+    // tangent space normal map with normals encoded as (n + 1) / 2
+    Normal(Texture),
+    // height map whose gradient tilts the normal, with strength in height
+    // per world unit so the same map looks alike on objects of any size
+    Bump { height: Texture, strength: f64 },
+}
+
+impl SurfaceMap {
+    pub fn perturb(&self, record: &mut HitRecord) {
+        let n = record.normal;
+        let t = record.tangent;
+        let b = n.cross(&t);
+        record.normal = match self {
+            Self::Normal(texture) => {
+                let m =
+                    texture.value(record.u, record.v, &record.p) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+                t * m.x() + b * m.y() + n * m.z()
+            }
+            Self::Bump { height, strength } => {
+                // texture coordinates covering the step, none where they
+                // don't change over the surface
+                let step = |dp: f64| if dp > 0.0 { BUMP_EPSILON / dp } else { 0.0 };
+                let (du, dv) = (step(record.dp_du), step(record.dp_dv));
+                let h0 = height.intensity(record.u, record.v, &record.p);
+                let h_t = height.intensity(record.u + du, record.v, &(record.p + t * BUMP_EPSILON));
+                let h_b = height.intensity(record.u, record.v + dv, &(record.p + b * BUMP_EPSILON));
+                let dh_dt = (h_t - h0) / BUMP_EPSILON;
+                let dh_db = (h_b - h0) / BUMP_EPSILON;
+                n - (t * dh_dt + b * dh_db) * *strength
+            }
+        }
+        .unit_vector();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean::Color;
+    use crate::texture::Image;
+    use std::sync::Arc;
+
+    // a black and a white half split at u = 0.5
+    fn step_map() -> SurfaceMap {
+        let pixels = vec![Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)];
+        SurfaceMap::Bump {
+            height: Texture::Image(Arc::new(Image::new(2, 1, pixels))),
+            strength: 1e-3,
+        }
+    }
+
+    fn record(dp_du: f64) -> HitRecord<'static> {
+        HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            u: 0.5 - 5e-4,
+            v: 0.5,
+            dp_du,
+            dp_dv: dp_du,
+            ..HitRecord::default()
+        }
+    }
+
+    #[test]
+    fn test_bump_step_in_world_space() {
+        // a world space step crosses the edge on a small surface only
+        let mut small = record(1.0);
+        step_map().perturb(&mut small);
+        assert!(small.normal.x() < -0.5);
+        let mut large = record(10.0);
+        step_map().perturb(&mut large);
+        assert_eq!(large.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+}
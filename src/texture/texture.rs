@@ -0,0 +1,41 @@
+use super::{perlin, Image};
+use crate::euclidean::{Color, Point3};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Texture {
+    Solid(Color),
+    Checker { odd: Color, even: Color, scale: f64 },
+    // gradient noise remapped to [0, 1]
+    Noise { scale: f64 },
+    Image(Arc<Image>),
+}
+
+impl Texture {
+    pub fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        match self {
+            Self::Solid(color) => *color,
+            Self::Checker { odd, even, scale } => {
+                let sines = (scale * p.x()).sin() * (scale * p.y()).sin() * (scale * p.z()).sin();
+                if sines < 0.0 {
+                    *odd
+                } else {
+                    *even
+                }
+            }
+            Self::Noise { scale } => {
+                let n = 0.5 * (1.0 + perlin().noise(&(*p * *scale)));
+                Color::new(n, n, n)
+            }
+            Self::Image(image) => image.sample(u, v),
+        }
+    }
+
+    // scalar value of the texture, e.g. for height and opacity maps
+    #[inline(always)]
+    pub fn intensity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        let c = self.value(u, v, p);
+        (c.x() + c.y() + c.z()) / 3.0
+    }
+}